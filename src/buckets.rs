@@ -0,0 +1,73 @@
+use crate::FileEntry;
+use crate::size;
+
+/// Size boundaries splitting files into tiers, as given by `--bucket-by`.
+/// Each boundary keeps the spelling the user typed so tier labels read the
+/// same way as the command line.
+#[derive(Clone)]
+pub struct Boundaries(Vec<(u64, String)>);
+
+pub struct Tier {
+    pub label: String,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+/// Parses a comma-separated list of sizes like `1MB,100MB,1GB`. The
+/// boundaries must be strictly increasing.
+pub fn parse_boundaries(value: &str) -> Result<Boundaries, String> {
+    let mut boundaries: Vec<(u64, String)> = Vec::new();
+
+    for part in value.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            return Err(format!("empty boundary in '{}'", value));
+        }
+        let bytes = size::parse_size(part)?;
+        if let Some((previous, label)) = boundaries.last()
+            && bytes <= *previous
+        {
+            return Err(format!(
+                "boundaries must be increasing, but '{}' is not larger than '{}'",
+                part, label
+            ));
+        }
+        boundaries.push((bytes, part.to_string()));
+    }
+
+    Ok(Boundaries(boundaries))
+}
+
+/// Sorts files into the tiers delimited by the boundaries, smallest tier
+/// first. A tier includes its lower boundary, so a file of exactly 1MB
+/// lands in `1MB–100MB`, not `<1MB`.
+pub fn bucket(entries: &[FileEntry], boundaries: &Boundaries) -> Vec<Tier> {
+    let bounds = &boundaries.0;
+    let mut tiers: Vec<Tier> = Vec::with_capacity(bounds.len() + 1);
+
+    tiers.push(Tier {
+        label: format!("<{}", bounds[0].1),
+        count: 0,
+        bytes: 0,
+    });
+    for pair in bounds.windows(2) {
+        tiers.push(Tier {
+            label: format!("{}–{}", pair[0].1, pair[1].1),
+            count: 0,
+            bytes: 0,
+        });
+    }
+    tiers.push(Tier {
+        label: format!(">{}", bounds[bounds.len() - 1].1),
+        count: 0,
+        bytes: 0,
+    });
+
+    for entry in entries {
+        let index = bounds.partition_point(|(bound, _)| *bound <= entry.size);
+        tiers[index].count += 1;
+        tiers[index].bytes += entry.size;
+    }
+
+    tiers
+}
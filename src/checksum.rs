@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use xxhash_rust::xxh3::Xxh3;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Algorithm {
+    Md5,
+    Sha256,
+    Blake3,
+    Xxh3,
+}
+
+enum Hasher {
+    Md5(Md5),
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+    Xxh3(Box<Xxh3>),
+}
+
+impl Hasher {
+    fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Md5 => Hasher::Md5(Md5::new()),
+            Algorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            Algorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+            Algorithm::Xxh3 => Hasher::Xxh3(Box::new(Xxh3::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(h) => h.update(data),
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+            Hasher::Xxh3(h) => h.update(data),
+        }
+    }
+
+    fn finish(self) -> String {
+        match self {
+            Hasher::Md5(h) => to_hex(&h.finalize()),
+            Hasher::Sha256(h) => to_hex(&h.finalize()),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+            Hasher::Xxh3(h) => format!("{:016x}", h.digest()),
+        }
+    }
+}
+
+/// Reads the whole file and returns its digest as a lowercase hex string.
+pub fn hash_file(path: &Path, algorithm: Algorithm) -> Result<String> {
+    let mut file = File::open(crate::longpath::fs_path(path))
+        .with_context(|| format!("Failed to open: {}", path.display()))?;
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .with_context(|| format!("Failed to read: {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finish())
+}
+
+/// Hashes the first, middle and last `SAMPLE_BYTES` of a `size`-byte file.
+/// Files that differ only outside those windows hash the same, so this is a
+/// cheap hint of equality, never proof.
+pub fn sample_hash(path: &Path, size: u64) -> Result<u64> {
+    const SAMPLE_BYTES: u64 = 4 * 1024;
+
+    let mut file = File::open(crate::longpath::fs_path(path))
+        .with_context(|| format!("Failed to open: {}", path.display()))?;
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0u8; SAMPLE_BYTES as usize];
+    let middle = (size / 2).saturating_sub(SAMPLE_BYTES / 2);
+    let last = size.saturating_sub(SAMPLE_BYTES);
+
+    for offset in [0, middle, last] {
+        file.seek(SeekFrom::Start(offset))
+            .with_context(|| format!("Failed to seek: {}", path.display()))?;
+        let read = (&mut file)
+            .take(SAMPLE_BYTES)
+            .read(&mut buffer)
+            .with_context(|| format!("Failed to read: {}", path.display()))?;
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.digest())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,54 @@
+use anyhow::Result;
+use std::path::Path;
+
+/// Orders paths by a locale's collation rules instead of byte by byte, using
+/// the C library's `strxfrm`, so `Äpfel` sorts next to `apple` in German.
+pub struct Collator(());
+
+impl Collator {
+    /// Selects the collation of `locale`, such as `de_DE.UTF-8`, for the
+    /// whole process. An empty name takes it from `LC_ALL`, `LC_COLLATE` or
+    /// `LANG`. Fails when the locale is not installed.
+    #[cfg(unix)]
+    pub fn new(locale: &str) -> Result<Self> {
+        let name = std::ffi::CString::new(locale)?;
+        // SAFETY: `name` is a valid C string, and this runs before any other
+        // thread uses the locale.
+        let selected = unsafe { libc::setlocale(libc::LC_COLLATE, name.as_ptr()) };
+        if selected.is_null() {
+            anyhow::bail!(
+                "Locale '{}' is not available; `locale -a` lists the installed ones",
+                locale
+            );
+        }
+        Ok(Self(()))
+    }
+
+    #[cfg(not(unix))]
+    pub fn new(_locale: &str) -> Result<Self> {
+        anyhow::bail!("--collate is only supported on Unix")
+    }
+
+    /// A key whose byte order is the collation order of `path`.
+    #[cfg(unix)]
+    pub fn key(&self, path: &Path) -> Vec<u8> {
+        let Ok(text) = std::ffi::CString::new(path.to_string_lossy().into_owned()) else {
+            return path.as_os_str().as_encoded_bytes().to_vec();
+        };
+        // SAFETY: with a zero length `strxfrm` writes nothing and returns the
+        // length of the key; the second call gets a buffer one byte longer,
+        // room for the key and its terminator.
+        unsafe {
+            let len = libc::strxfrm(std::ptr::null_mut(), text.as_ptr(), 0);
+            let mut key = vec![0u8; len + 1];
+            libc::strxfrm(key.as_mut_ptr().cast(), text.as_ptr(), key.len());
+            key.truncate(len);
+            key
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn key(&self, path: &Path) -> Vec<u8> {
+        path.as_os_str().as_encoded_bytes().to_vec()
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::FileEntry;
+use crate::ext::ExtensionRules;
+
+/// How much of each file is compressed to estimate its ratio.
+const SAMPLE_BYTES: u64 = 64 * 1024;
+/// Header and trailer bytes gzip adds around the compressed data.
+const GZIP_OVERHEAD: u64 = 18;
+
+pub struct Estimate {
+    pub key: String,
+    pub count: usize,
+    pub bytes: u64,
+    pub compressed: u64,
+}
+
+/// Estimates the compressed size of a `size`-byte file by compressing its
+/// first 64 KB at gzip level 1 and scaling that ratio to the whole file.
+/// A file that reads empty, whatever its recorded size, is assumed not to
+/// compress.
+pub fn estimate(path: &Path, size: u64) -> Result<u64> {
+    let mut sample = Vec::new();
+    File::open(crate::longpath::fs_path(path))
+        .with_context(|| format!("Failed to open: {}", path.display()))?
+        .take(SAMPLE_BYTES)
+        .read_to_end(&mut sample)
+        .with_context(|| format!("Failed to read: {}", path.display()))?;
+    if sample.is_empty() {
+        return Ok(size);
+    }
+
+    let mut encoder = GzEncoder::new(ByteCount(0), Compression::fast());
+    encoder
+        .write_all(&sample)
+        .and_then(|()| encoder.try_finish())
+        .with_context(|| format!("Failed to compress: {}", path.display()))?;
+    let ByteCount(written) = encoder.get_ref();
+
+    let sampled = sample.len() as f64;
+    let compressed = written.saturating_sub(GZIP_OVERHEAD) as f64;
+    Ok((size as f64 * compressed / sampled).round() as u64)
+}
+
+/// A sink that only counts the bytes written to it.
+struct ByteCount(u64);
+
+impl Write for ByteCount {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sums the estimates by extension, largest original size first.
+pub fn by_extension(entries: &[FileEntry], rules: &ExtensionRules) -> Vec<Estimate> {
+    let mut estimates: HashMap<String, Estimate> = HashMap::new();
+
+    for entry in entries {
+        let Some(compressed) = entry.compressed else {
+            continue;
+        };
+        let key = rules
+            .extension(&entry.path)
+            .unwrap_or_else(|| "<none>".to_string());
+        let estimate = estimates.entry(key.clone()).or_insert(Estimate {
+            key,
+            count: 0,
+            bytes: 0,
+            compressed: 0,
+        });
+        estimate.count += 1;
+        estimate.bytes += entry.size;
+        estimate.compressed += compressed;
+    }
+
+    let mut estimates: Vec<Estimate> = estimates.into_values().collect();
+    estimates.sort_by(|a, b| {
+        Reverse(a.bytes)
+            .cmp(&Reverse(b.bytes))
+            .then_with(|| a.key.cmp(&b.key))
+    });
+    estimates
+}
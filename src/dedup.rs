@@ -0,0 +1,186 @@
+use anyhow::Result;
+use colored::*;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+
+use crate::FileEntry;
+use crate::theme::Themed;
+
+/// Identifies the physical file behind a path, so different routes to the
+/// same data can be recognised.
+#[derive(Clone, Hash, PartialEq, Eq)]
+pub enum FileId {
+    #[cfg(unix)]
+    Inode { dev: u64, ino: u64 },
+    #[cfg(not(unix))]
+    Canonical(std::path::PathBuf),
+}
+
+#[cfg(unix)]
+pub fn file_id(_path: &Path, metadata: &Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(FileId::Inode {
+        dev: metadata.dev(),
+        ino: metadata.ino(),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn file_id(path: &Path, _metadata: &Metadata) -> Option<FileId> {
+    std::fs::canonicalize(path).ok().map(FileId::Canonical)
+}
+
+/// The number of hard links to the file, where the platform reports it.
+#[cfg(unix)]
+pub fn link_count(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.nlink())
+}
+
+#[cfg(not(unix))]
+pub fn link_count(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Matched files that have more than one hard link.
+pub struct HardlinkStats {
+    pub files: usize,
+    /// Distinct files on disk among them.
+    pub inodes: usize,
+}
+
+/// Counts the hard-linked entries. Needs `links` and `id` to be recorded.
+pub fn hardlink_stats(entries: &[FileEntry]) -> HardlinkStats {
+    let linked: Vec<&FileEntry> = entries
+        .iter()
+        .filter(|entry| entry.links.is_some_and(|links| links > 1))
+        .collect();
+    let inodes: HashSet<&FileId> = linked
+        .iter()
+        .filter_map(|entry| entry.id.as_ref())
+        .collect();
+    HardlinkStats {
+        files: linked.len(),
+        inodes: inodes.len(),
+    }
+}
+
+/// Paths that were collapsed into the entry kept for the same file.
+pub struct CollapsedGroup {
+    pub kept: PathBuf,
+    pub size: u64,
+    pub dropped: Vec<PathBuf>,
+}
+
+impl CollapsedGroup {
+    /// Bytes a naive sum would have counted on top of the kept entry.
+    pub fn saved(&self) -> u64 {
+        self.size.saturating_mul(self.dropped.len() as u64)
+    }
+}
+
+/// Drops symlinks whose target is already counted, either directly or through
+/// another symlink. Returns `(dropped, kept)` pairs.
+pub fn collapse_symlinks(results: &mut Vec<Result<FileEntry>>) -> Vec<(PathBuf, PathBuf)> {
+    let mut seen: HashMap<FileId, PathBuf> = results
+        .iter()
+        .filter_map(|result| result.as_ref().ok())
+        .filter(|entry| !entry.is_symlink)
+        .filter_map(|entry| Some((entry.id.clone()?, entry.path.clone())))
+        .collect();
+
+    let mut collapsed = Vec::new();
+    results.retain(|result| match result {
+        Ok(entry) if entry.is_symlink => match &entry.id {
+            Some(id) => match seen.get(id) {
+                Some(kept) => {
+                    collapsed.push((entry.path.clone(), kept.clone()));
+                    false
+                }
+                None => {
+                    seen.insert(id.clone(), entry.path.clone());
+                    true
+                }
+            },
+            None => true,
+        },
+        _ => true,
+    });
+    collapsed
+}
+
+/// Groups `(dropped, kept)` pairs by their kept entry, most bytes saved
+/// first. Pairs whose kept path was not measured are left out.
+pub fn collapsed_groups(
+    collapsed: &[(PathBuf, PathBuf)],
+    entries: &[FileEntry],
+) -> Vec<CollapsedGroup> {
+    let sizes: HashMap<&Path, u64> = entries
+        .iter()
+        .map(|entry| (entry.path.as_path(), entry.size))
+        .collect();
+
+    let mut groups: HashMap<&Path, CollapsedGroup> = HashMap::new();
+    for (dropped, kept) in collapsed {
+        let Some(&size) = sizes.get(kept.as_path()) else {
+            continue;
+        };
+        groups
+            .entry(kept)
+            .or_insert_with(|| CollapsedGroup {
+                kept: kept.clone(),
+                size,
+                dropped: Vec::new(),
+            })
+            .dropped
+            .push(dropped.clone());
+    }
+
+    let mut groups: Vec<CollapsedGroup> = groups.into_values().collect();
+    groups.sort_by(|a, b| b.saved().cmp(&a.saved()).then_with(|| a.kept.cmp(&b.kept)));
+    groups
+}
+
+/// Keeps the first path reaching each physical file, comparing canonical
+/// paths so `a/../b.txt`, `./b.txt` and routes through symlinked directories
+/// collapse together. Paths that cannot be canonicalized are compared
+/// textually. Returns the kept paths and `(dropped, kept)` pairs.
+pub fn canonical_dedup(paths: Vec<PathBuf>) -> (Vec<PathBuf>, Vec<(PathBuf, PathBuf)>) {
+    let keys: Vec<PathBuf> = paths
+        .par_iter()
+        .map(|path| {
+            std::fs::canonicalize(crate::longpath::fs_path(path)).unwrap_or_else(|e| {
+                eprintln!(
+                    "{}: Cannot canonicalize {} ({}), comparing it textually",
+                    "Warning".warn().bold(),
+                    path.display(),
+                    e
+                );
+                path.clone()
+            })
+        })
+        .collect();
+
+    let mut first_seen: HashMap<PathBuf, usize> = HashMap::new();
+    let mut kept = Vec::new();
+    let mut collapsed = Vec::new();
+
+    for (path, key) in paths.into_iter().zip(keys) {
+        match first_seen.get(&key) {
+            Some(&index) => {
+                let kept_path: &PathBuf = &kept[index];
+                collapsed.push((path, kept_path.clone()));
+            }
+            None => {
+                first_seen.insert(key, kept.len());
+                kept.push(path);
+            }
+        }
+    }
+
+    (kept, collapsed)
+}
@@ -0,0 +1,100 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::encoding::PathEncoding;
+use crate::theme::Themed;
+
+/// Turns matched paths into the strings shown to the user. Only affects
+/// presentation; gathering and totals always work with the real paths.
+pub struct PathDisplay {
+    strip_prefix: Option<PathBuf>,
+    pattern_roots: Vec<PathBuf>,
+    encoding: PathEncoding,
+    debug: bool,
+    /// Show paths untouched, as gathered.
+    raw: bool,
+}
+
+impl PathDisplay {
+    pub fn new(
+        strip_prefix: Option<PathBuf>,
+        pattern_roots: Vec<PathBuf>,
+        encoding: PathEncoding,
+        debug: bool,
+    ) -> Self {
+        Self {
+            strip_prefix,
+            pattern_roots,
+            encoding,
+            debug,
+            raw: false,
+        }
+    }
+
+    /// Shows every path exactly as gathered, without any of the relative
+    /// path or encoding options.
+    pub fn raw() -> Self {
+        Self {
+            strip_prefix: None,
+            pattern_roots: Vec::new(),
+            encoding: PathEncoding::Lossy,
+            debug: false,
+            raw: true,
+        }
+    }
+
+    pub fn show(&self, path: &Path) -> String {
+        if self.raw {
+            return path.as_os_str().to_string_lossy().into_owned();
+        }
+        if let Some(prefix) = &self.strip_prefix {
+            return match path.strip_prefix(prefix) {
+                Ok(rest) => self.show_relative(rest),
+                Err(_) => {
+                    if self.debug {
+                        eprintln!(
+                            "  {} {} is not under {}, showing it unchanged",
+                            "Note:".label(),
+                            path.display(),
+                            prefix.display()
+                        );
+                    }
+                    self.encoding.encode(path)
+                }
+            };
+        }
+
+        let rest = self
+            .pattern_roots
+            .iter()
+            .filter_map(|root| path.strip_prefix(root).ok())
+            .min_by_key(|rest| rest.components().count())
+            .unwrap_or(path);
+        self.show_relative(rest)
+    }
+
+    fn show_relative(&self, path: &Path) -> String {
+        if path.as_os_str().is_empty() {
+            ".".to_string()
+        } else {
+            self.encoding.encode(path)
+        }
+    }
+}
+
+/// Returns the leading directories of a pattern that contain no glob
+/// metacharacters, e.g. `src/foo` for `src/foo/**/*.rs`. The final component
+/// is never part of the root, so a literal file path yields its parent.
+pub fn pattern_root(pattern: &str) -> PathBuf {
+    let components: Vec<Component> = Path::new(pattern).components().collect();
+    let literal = components
+        .iter()
+        .take(components.len().saturating_sub(1))
+        .take_while(|component| {
+            !component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '['])
+        });
+
+    literal.collect()
+}
@@ -0,0 +1,119 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use clap::ValueEnum;
+use std::fmt::Write;
+use std::path::Path;
+
+/// How paths that are not valid UTF-8 are turned into text.
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum PathEncoding {
+    /// Replace invalid sequences with U+FFFD, like `Path::display`
+    #[default]
+    Lossy,
+    /// Backslash-escape invalid bytes and control characters as `\xNN`, and
+    /// backslashes as `\\`
+    Escape,
+    /// Base64 of the raw path bytes. JSON keeps valid UTF-8 paths as text
+    Base64,
+}
+
+impl PathEncoding {
+    pub fn encode(self, path: &Path) -> String {
+        match self {
+            PathEncoding::Lossy => path.display().to_string(),
+            PathEncoding::Escape => escape(path.as_os_str().as_encoded_bytes()),
+            PathEncoding::Base64 => STANDARD.encode(path.as_os_str().as_encoded_bytes()),
+        }
+    }
+}
+
+fn escape(mut bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+
+    while !bytes.is_empty() {
+        let (valid, invalid) = match std::str::from_utf8(bytes) {
+            Ok(valid) => (valid, &[][..]),
+            Err(e) => {
+                let (valid, rest) = bytes.split_at(e.valid_up_to());
+                let invalid_len = e.error_len().unwrap_or(rest.len());
+                (
+                    std::str::from_utf8(valid).unwrap_or_default(),
+                    &rest[..invalid_len],
+                )
+            }
+        };
+
+        for c in valid.chars() {
+            if c == '\\' {
+                escaped.push_str("\\\\");
+            } else if c.is_control() {
+                for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                    let _ = write!(escaped, "\\x{:02x}", byte);
+                }
+            } else {
+                escaped.push(c);
+            }
+        }
+        for byte in invalid {
+            let _ = write!(escaped, "\\x{:02x}", byte);
+        }
+
+        bytes = &bytes[valid.len() + invalid.len()..];
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unescape(escaped: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut rest = escaped.as_bytes();
+        while let Some((&byte, tail)) = rest.split_first() {
+            match (byte, tail) {
+                (b'\\', [b'\\', tail @ ..]) => {
+                    bytes.push(b'\\');
+                    rest = tail;
+                }
+                (b'\\', [b'x', hi, lo, tail @ ..]) => {
+                    let hex = [*hi, *lo];
+                    let hex = std::str::from_utf8(&hex).unwrap();
+                    bytes.push(u8::from_str_radix(hex, 16).unwrap());
+                    rest = tail;
+                }
+                (b'\\', _) => panic!("dangling backslash in {escaped:?}"),
+                _ => {
+                    bytes.push(byte);
+                    rest = tail;
+                }
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn escapes_backslashes_control_characters_and_invalid_bytes() {
+        assert_eq!(escape(b"dir/file.txt"), "dir/file.txt");
+        assert_eq!(escape(b"a\\xff"), "a\\\\xff");
+        assert_eq!(escape(b"tab\there\n"), "tab\\x09here\\x0a");
+        assert_eq!(escape(b"caf\xc3\xa9 \xff"), "caf\u{e9} \\xff");
+    }
+
+    #[test]
+    fn escaped_paths_round_trip() {
+        let samples: [&[u8]; 7] = [
+            b"",
+            b"plain/path.rs",
+            b"back\\slash\\",
+            b"\\xff looks escaped",
+            b"line\nbreak\x7f\x00",
+            b"caf\xc3\xa9/\xe2\x82",
+            b"\xff\xfe\\\x80",
+        ];
+        for bytes in samples {
+            assert_eq!(unescape(&escape(bytes)), bytes, "{bytes:?}");
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// The globs given with `--exclude` and `--exclude-from`, compiled into one
+/// set. A glob is matched against the whole path as found, minus any leading
+/// `./`, and `*` also crosses directories, so `*.log` excludes logs anywhere.
+pub struct Excludes {
+    patterns: Vec<String>,
+    set: GlobSet,
+}
+
+impl Excludes {
+    /// Reads `file` one glob per line, skipping blank lines and lines
+    /// starting with `#`, and adds the globs to `patterns`.
+    pub fn load(patterns: &[String], file: Option<&Path>) -> Result<Self> {
+        let mut patterns = patterns.to_vec();
+        if let Some(file) = file {
+            let contents = fs::read_to_string(file)
+                .with_context(|| format!("Failed to read exclude file: {}", file.display()))?;
+            patterns.extend(
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &patterns {
+            let glob = Glob::new(pattern)
+                .with_context(|| format!("Invalid exclude pattern: {}", pattern))?;
+            builder.add(glob);
+        }
+        let set = builder
+            .build()
+            .context("Failed to compile exclude patterns")?;
+
+        Ok(Self { patterns, set })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        self.set.is_match(normalize(path))
+    }
+
+    /// The indices of every pattern matching `path`.
+    pub fn matching(&self, path: &Path) -> Vec<usize> {
+        self.set.matches(normalize(path))
+    }
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .skip_while(|component| matches!(component, Component::CurDir))
+        .collect()
+}
@@ -0,0 +1,272 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::FileEntry;
+
+const DEFAULT_MERGES: &str = "jpeg=jpg,jpe=jpg,yaml=yml,htm=html,tiff=tif,mpeg=mpg,markdown=md";
+pub const DEFAULT_COMPOUNDS: &str = "tar.gz,tar.bz2,tar.xz,tar.zst";
+
+/// How file extensions are derived for filtering and grouping.
+///
+/// The extension is whatever follows the last dot of the file name, with
+/// these rules:
+///
+/// - `a` has no extension.
+/// - `a.` has no extension (the empty string after the dot is not one).
+/// - `.bashrc` has no extension: a leading dot with no other dot marks a
+///   hidden file, not an extension.
+/// - `.config.toml` has extension `toml`.
+/// - `a.tar.gz` has extension `gz`, or `tar.gz` when `tar.gz` is one of the
+///   `compound` suffixes. Compound suffixes match regardless of case.
+///
+/// Extensions are lowercased, so `IMG.PNG` and `img.png` both have extension
+/// `png`, unless `case_sensitive` is set. Values compared against extensions,
+/// such as `--filter 'ext == PNG'` or merge mappings, go through
+/// [`ExtensionRules::normalize`] so both sides follow the same rule.
+#[derive(Default)]
+pub struct ExtensionRules {
+    merge: HashMap<String, String>,
+    /// Lowercased compound suffixes, longest first.
+    compound: Vec<String>,
+    case_sensitive: bool,
+}
+
+impl ExtensionRules {
+    /// `merge` is a list like `jpeg=jpg,yaml=yml`; `default` selects the
+    /// built-in set of common aliases. `compound` is a list of suffixes like
+    /// `tar.gz,d.ts` to treat as one extension.
+    pub fn new(
+        merge: Option<&str>,
+        compound: Option<&str>,
+        case_sensitive: bool,
+    ) -> Result<Self, String> {
+        let mut rules = Self {
+            merge: HashMap::new(),
+            compound: compound
+                .map(parse_compounds)
+                .transpose()?
+                .unwrap_or_default(),
+            case_sensitive,
+        };
+        let merge = match merge {
+            None => HashMap::new(),
+            Some("default") => parse_merges(DEFAULT_MERGES)?,
+            Some(spec) => parse_merges(spec)?,
+        };
+        rules.merge = merge
+            .into_iter()
+            .map(|(from, to)| (rules.normalize(&from), rules.normalize(&to)))
+            .collect();
+        Ok(rules)
+    }
+
+    /// Applies the case rule to an extension written by the user.
+    pub fn normalize(&self, ext: &str) -> String {
+        if self.case_sensitive {
+            ext.to_string()
+        } else {
+            ext.to_lowercase()
+        }
+    }
+
+    pub fn extension(&self, path: &Path) -> Option<String> {
+        let ext = path.extension()?.to_string_lossy().into_owned();
+        if ext.is_empty() {
+            return None;
+        }
+
+        let ext = self.compound_suffix(path).unwrap_or(ext);
+        let ext = self.normalize(&ext);
+
+        Some(self.merge.get(&ext).cloned().unwrap_or(ext))
+    }
+}
+
+impl ExtensionRules {
+    /// The compound suffix `path` ends with, spelled as in the file name.
+    /// Something other than a leading dot must come before it, so `.tar.gz`
+    /// alone is a hidden file with extension `gz`.
+    fn compound_suffix(&self, path: &Path) -> Option<String> {
+        let name = path.file_name()?.to_string_lossy();
+        self.compound.iter().find_map(|suffix| {
+            let start = name.len().checked_sub(suffix.len())?;
+            let (stem, ext) = name.split_at_checked(start)?;
+            let stem = stem.strip_suffix('.')?;
+            (ext.eq_ignore_ascii_case(suffix) && !stem.trim_start_matches('.').is_empty())
+                .then(|| ext.to_string())
+        })
+    }
+}
+
+/// An extension written with more than one casing, e.g. `png` and `PNG`.
+pub struct MixedCase {
+    /// The lowercased extension.
+    pub ext: String,
+    /// Every casing in use with the files using it, ordered by casing.
+    pub casings: Vec<(String, Vec<PathBuf>)>,
+}
+
+/// Groups files by lowercased extension and returns the groups whose files
+/// spell the extension in more than one way.
+pub fn mixed_case(entries: &[FileEntry], compound: Option<&str>) -> Vec<MixedCase> {
+    let exact = ExtensionRules::new(None, compound, true).unwrap_or_default();
+    let mut groups: BTreeMap<String, BTreeMap<String, Vec<PathBuf>>> = BTreeMap::new();
+
+    for entry in entries {
+        if let Some(ext) = exact.extension(&entry.path) {
+            groups
+                .entry(ext.to_lowercase())
+                .or_default()
+                .entry(ext)
+                .or_default()
+                .push(entry.path.clone());
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, casings)| casings.len() > 1)
+        .map(|(ext, casings)| MixedCase {
+            ext,
+            casings: casings.into_iter().collect(),
+        })
+        .collect()
+}
+
+fn parse_compounds(spec: &str) -> Result<Vec<String>, String> {
+    let mut compounds = spec
+        .split(',')
+        .map(|suffix| suffix.trim().trim_start_matches('.').to_lowercase())
+        .filter(|suffix| !suffix.is_empty())
+        .map(|suffix| {
+            if suffix.contains('.') {
+                Ok(suffix)
+            } else {
+                Err(format!(
+                    "invalid compound extension '{}', expected two or more parts like tar.gz",
+                    suffix
+                ))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    // The longest suffix wins, so `tar.gz` does not hide `pkg.tar.gz`.
+    compounds.sort_by_key(|suffix| std::cmp::Reverse(suffix.len()));
+    Ok(compounds)
+}
+
+fn parse_merges(spec: &str) -> Result<HashMap<String, String>, String> {
+    spec.split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (from, to) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("invalid extension mapping '{}', expected from=to", pair))?;
+            let from = from.trim().trim_start_matches('.');
+            let to = to.trim().trim_start_matches('.');
+            Ok((from.to_string(), to.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ext(rules: &ExtensionRules, path: &str) -> Option<String> {
+        rules.extension(Path::new(path))
+    }
+
+    #[test]
+    fn dotfiles_have_no_extension() {
+        let rules = ExtensionRules::default();
+        assert_eq!(ext(&rules, ".bashrc"), None);
+        assert_eq!(ext(&rules, "dir/.gitignore"), None);
+        assert_eq!(ext(&rules, ".config.toml").as_deref(), Some("toml"));
+        assert_eq!(ext(&rules, "Makefile"), None);
+        assert_eq!(ext(&rules, "trailing."), None);
+    }
+
+    #[test]
+    fn compound_extensions_are_off_by_default() {
+        let rules = ExtensionRules::default();
+        assert_eq!(ext(&rules, "a.tar.gz").as_deref(), Some("gz"));
+    }
+
+    #[test]
+    fn default_compounds_cover_tar_archives() {
+        let rules = ExtensionRules::new(None, Some(DEFAULT_COMPOUNDS), false).unwrap();
+        assert_eq!(ext(&rules, "a.tar.gz").as_deref(), Some("tar.gz"));
+        assert_eq!(ext(&rules, "b.tar.zst").as_deref(), Some("tar.zst"));
+        assert_eq!(ext(&rules, "c.tar.lz4").as_deref(), Some("lz4"));
+        assert_eq!(ext(&rules, "d.gz").as_deref(), Some("gz"));
+        // A hidden file named after the suffix has nothing before it.
+        assert_eq!(ext(&rules, ".tar.gz").as_deref(), Some("gz"));
+    }
+
+    #[test]
+    fn custom_compounds_prefer_the_longest_suffix() {
+        let rules = ExtensionRules::new(None, Some(".d.ts, tar.gz,pkg.tar.gz"), false).unwrap();
+        assert_eq!(ext(&rules, "index.d.ts").as_deref(), Some("d.ts"));
+        assert_eq!(ext(&rules, "index.ts").as_deref(), Some("ts"));
+        assert_eq!(ext(&rules, "x.pkg.tar.gz").as_deref(), Some("pkg.tar.gz"));
+        assert_eq!(ext(&rules, "x.tar.gz").as_deref(), Some("tar.gz"));
+    }
+
+    #[test]
+    fn single_part_compounds_are_rejected() {
+        assert!(ExtensionRules::new(None, Some("tar.gz,gz"), false).is_err());
+    }
+
+    #[test]
+    fn extensions_are_lowercased_by_default() {
+        let rules = ExtensionRules::default();
+        assert_eq!(ext(&rules, "IMG.PNG"), ext(&rules, "img.png"));
+        assert_eq!(ext(&rules, "Photo.Png").as_deref(), Some("png"));
+        assert_eq!(rules.normalize("PNG"), "png");
+    }
+
+    #[test]
+    fn case_sensitive_rules_keep_the_spelling() {
+        let rules = ExtensionRules::new(None, None, true).unwrap();
+        assert_eq!(ext(&rules, "IMG.PNG").as_deref(), Some("PNG"));
+        assert_eq!(ext(&rules, "img.png").as_deref(), Some("png"));
+        assert_eq!(rules.normalize("PNG"), "PNG");
+    }
+
+    #[test]
+    fn merges_follow_the_case_rule() {
+        let rules = ExtensionRules::new(Some("JPEG=jpg"), None, false).unwrap();
+        assert_eq!(ext(&rules, "a.jpeg").as_deref(), Some("jpg"));
+        assert_eq!(ext(&rules, "b.JPEG").as_deref(), Some("jpg"));
+    }
+
+    #[test]
+    fn filters_and_groups_agree_on_case() {
+        let metadata = std::fs::metadata(env!("CARGO_MANIFEST_DIR")).unwrap();
+        let filter = crate::filter::parse("ext == PNG").unwrap();
+        let matches = |rules: &ExtensionRules, path: &str| {
+            filter.matches(Path::new(path), &metadata, rules).unwrap()
+        };
+
+        let rules = ExtensionRules::default();
+        assert!(matches(&rules, "IMG.PNG"));
+        assert!(matches(&rules, "img.png"));
+
+        let exact = ExtensionRules::new(None, None, true).unwrap();
+        assert!(matches(&exact, "IMG.PNG"));
+        assert!(!matches(&exact, "img.png"));
+    }
+
+    #[test]
+    fn mixed_case_lists_every_spelling() {
+        let entries: Vec<FileEntry> = ["a.png", "b.PNG", "c.Png", "d.txt"]
+            .into_iter()
+            .map(|path| FileEntry::recorded(PathBuf::from(path), 1))
+            .collect();
+        let mixed = mixed_case(&entries, None);
+        assert_eq!(mixed.len(), 1);
+        assert_eq!(mixed[0].ext, "png");
+        let casings: Vec<&str> = mixed[0].casings.iter().map(|(c, _)| c.as_str()).collect();
+        assert_eq!(casings, ["PNG", "Png", "png"]);
+    }
+}
@@ -0,0 +1,332 @@
+use anyhow::{Context, Result};
+use std::fs::Metadata;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::ext::ExtensionRules;
+use crate::size::parse_size;
+use crate::timefilter::parse_time_point;
+
+/// A parsed `--filter` expression such as `size > 1MB && mtime < 30d`.
+///
+/// Grammar, loosest binding first:
+///
+/// ```text
+/// expr       := and ("||" and)*
+/// and        := unary ("&&" unary)*
+/// unary      := "!" unary | "(" expr ")" | comparison
+/// comparison := field op value
+/// ```
+///
+/// `size` compares against sizes (`1MB`), `mtime`/`atime` against times
+/// (`30d` meaning 30 days ago, or `2024-01-31`), so `mtime < 30d` means
+/// "modified more than 30 days ago". `ext` and `name` compare strings with
+/// `==`/`!=`, or a glob pattern with `~`; `ext` follows the case rule of
+/// [`ExtensionRules`].
+#[derive(Clone, Debug)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Size(Op, u64),
+    Modified(Op, SystemTime),
+    Accessed(Op, SystemTime),
+    Ext(Op, String),
+    Name(Op, String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    Glob,
+}
+
+impl Filter {
+    pub fn matches(
+        &self,
+        path: &Path,
+        metadata: &Metadata,
+        rules: &ExtensionRules,
+    ) -> Result<bool> {
+        Ok(match self {
+            Filter::And(a, b) => {
+                a.matches(path, metadata, rules)? && b.matches(path, metadata, rules)?
+            }
+            Filter::Or(a, b) => {
+                a.matches(path, metadata, rules)? || b.matches(path, metadata, rules)?
+            }
+            Filter::Not(inner) => !inner.matches(path, metadata, rules)?,
+            Filter::Size(op, value) => compare(*op, &metadata.len(), value),
+            Filter::Modified(op, value) => {
+                let time = metadata.modified().with_context(|| {
+                    format!("Modification time is not available for: {}", path.display())
+                })?;
+                compare(*op, &time, value)
+            }
+            Filter::Accessed(op, value) => {
+                let time = metadata.accessed().with_context(|| {
+                    format!("Access time is not available for: {}", path.display())
+                })?;
+                compare(*op, &time, value)
+            }
+            Filter::Ext(op, value) => {
+                let ext = rules.extension(path).unwrap_or_default();
+                compare_text(*op, &ext, &rules.normalize(value))
+            }
+            Filter::Name(op, value) => {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                compare_text(*op, &name, value)
+            }
+        })
+    }
+}
+
+fn compare<T: PartialOrd>(op: Op, actual: &T, expected: &T) -> bool {
+    match op {
+        Op::Lt => actual < expected,
+        Op::Le => actual <= expected,
+        Op::Gt => actual > expected,
+        Op::Ge => actual >= expected,
+        Op::Eq => actual == expected,
+        Op::Ne => actual != expected,
+        Op::Glob => false,
+    }
+}
+
+fn compare_text(op: Op, actual: &str, expected: &str) -> bool {
+    match op {
+        Op::Glob => glob::Pattern::new(expected).is_ok_and(|pattern| pattern.matches(actual)),
+        op => compare(op, &actual, &expected),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+struct Lexed {
+    token: Token,
+    start: usize,
+    len: usize,
+}
+
+/// Parses a filter expression. Errors quote the expression with the
+/// offending token underlined.
+pub fn parse(input: &str) -> Result<Filter, String> {
+    let tokens = lex(input)?;
+    let mut parser = Parser {
+        input,
+        tokens,
+        position: 0,
+    };
+    let filter = parser.expr()?;
+    match parser.tokens.get(parser.position) {
+        None => Ok(filter),
+        Some(_) => Err(parser.error_here("expected '&&', '||' or end of expression")),
+    }
+}
+
+fn lex(input: &str) -> Result<Vec<Lexed>, String> {
+    let mut tokens = Vec::new();
+    let bytes = input.as_bytes();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let rest = &input[index..];
+        let c = bytes[index];
+        if c.is_ascii_whitespace() {
+            index += 1;
+            continue;
+        }
+
+        let symbol = [
+            ("&&", Token::And),
+            ("||", Token::Or),
+            (">=", Token::Op(Op::Ge)),
+            ("<=", Token::Op(Op::Le)),
+            ("==", Token::Op(Op::Eq)),
+            ("!=", Token::Op(Op::Ne)),
+            (">", Token::Op(Op::Gt)),
+            ("<", Token::Op(Op::Lt)),
+            ("~", Token::Op(Op::Glob)),
+            ("!", Token::Not),
+            ("(", Token::Open),
+            (")", Token::Close),
+        ]
+        .into_iter()
+        .find(|(text, _)| rest.starts_with(text));
+
+        if let Some((text, token)) = symbol {
+            tokens.push(Lexed {
+                token,
+                start: index,
+                len: text.len(),
+            });
+            index += text.len();
+            continue;
+        }
+
+        if c == b'"' || c == b'\'' {
+            let end = rest[1..]
+                .find(c as char)
+                .ok_or_else(|| highlight(input, index, rest.len(), "unterminated string"))?;
+            tokens.push(Lexed {
+                token: Token::Word(rest[1..=end].to_string()),
+                start: index,
+                len: end + 2,
+            });
+            index += end + 2;
+            continue;
+        }
+
+        let len = rest
+            .find(|c: char| c.is_whitespace() || "&|<>=!~()\"'".contains(c))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(highlight(input, index, 1, "unexpected character"));
+        }
+        tokens.push(Lexed {
+            token: Token::Word(rest[..len].to_string()),
+            start: index,
+            len,
+        });
+        index += len;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Lexed>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|lexed| &lexed.token)
+    }
+
+    fn error_here(&self, message: &str) -> String {
+        match self.tokens.get(self.position) {
+            Some(lexed) => highlight(self.input, lexed.start, lexed.len, message),
+            None => highlight(self.input, self.input.len(), 1, message),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Filter, String> {
+        let mut left = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            left = Filter::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut left = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            left = Filter::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Filter, String> {
+        match self.peek() {
+            Some(Token::Not) => {
+                self.position += 1;
+                Ok(Filter::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::Open) => {
+                self.position += 1;
+                let inner = self.expr()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(self.error_here("expected ')'"));
+                }
+                self.position += 1;
+                Ok(inner)
+            }
+            _ => self.comparison(),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Filter, String> {
+        let field = match self.peek() {
+            Some(Token::Word(word)) => word.clone(),
+            _ => return Err(self.error_here("expected a field: size, mtime, atime, ext or name")),
+        };
+        let field_position = self.position;
+        self.position += 1;
+
+        let op = match self.peek() {
+            Some(Token::Op(op)) => *op,
+            _ => return Err(self.error_here("expected a comparison operator")),
+        };
+        let op_position = self.position;
+        self.position += 1;
+
+        let value = match self.peek() {
+            Some(Token::Word(word)) => word.clone(),
+            _ => return Err(self.error_here("expected a value")),
+        };
+        let value_error = |parser: &Self, message: String| {
+            let lexed = &parser.tokens[parser.position];
+            highlight(parser.input, lexed.start, lexed.len, &message)
+        };
+
+        let is_text = matches!(field.as_str(), "ext" | "name");
+        if op == Op::Glob && !is_text {
+            self.position = op_position;
+            return Err(self.error_here("'~' only applies to ext and name"));
+        }
+
+        let filter = match field.as_str() {
+            "size" => Filter::Size(op, parse_size(&value).map_err(|e| value_error(self, e))?),
+            "mtime" => Filter::Modified(
+                op,
+                parse_time_point(&value).map_err(|e| value_error(self, e))?,
+            ),
+            "atime" => Filter::Accessed(
+                op,
+                parse_time_point(&value).map_err(|e| value_error(self, e))?,
+            ),
+            "ext" => Filter::Ext(op, value.trim_start_matches('.').to_string()),
+            "name" => Filter::Name(op, value),
+            _ => {
+                self.position = field_position;
+                return Err(
+                    self.error_here("unknown field; expected size, mtime, atime, ext or name")
+                );
+            }
+        };
+        self.position += 1;
+        Ok(filter)
+    }
+}
+
+fn highlight(input: &str, start: usize, len: usize, message: &str) -> String {
+    let column = input[..start.min(input.len())].chars().count();
+    format!(
+        "{}\n  {}\n  {}{}",
+        message,
+        input,
+        " ".repeat(column),
+        "^".repeat(len.max(1))
+    )
+}
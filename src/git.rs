@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// Paths tracked by git, relative to the current directory, as reported by
+/// `git ls-files`.
+pub struct TrackedFiles {
+    paths: HashSet<PathBuf>,
+    cwd: PathBuf,
+}
+
+impl TrackedFiles {
+    pub fn load() -> Result<Self> {
+        let output = Command::new("git")
+            .args(["ls-files", "-z"])
+            .output()
+            .context("Failed to run git; is it installed?")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "--git-tracked requires the current directory to be inside a git repository: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let paths = output
+            .stdout
+            .split(|byte| *byte == 0)
+            .filter(|path| !path.is_empty())
+            .map(|path| PathBuf::from(String::from_utf8_lossy(path).into_owned()))
+            .collect();
+
+        Ok(Self {
+            paths,
+            cwd: std::env::current_dir().context("Failed to get current directory")?,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(&self.cwd).unwrap_or(path);
+        let normalized: PathBuf = relative
+            .components()
+            .filter(|component| !matches!(component, Component::CurDir))
+            .collect();
+        self.paths.contains(&normalized)
+    }
+}
+
+/// The size of every file in the tree of `rev`, keyed by path relative to the
+/// current directory, as reported by `git ls-tree`. Sizes are read from the
+/// object database, so nothing is checked out. Submodules have no size in the
+/// tree and are skipped.
+pub fn tree_sizes(rev: &str) -> Result<BTreeMap<String, u64>> {
+    let output = Command::new("git")
+        .args(["ls-tree", "-r", "-l", "-z", rev])
+        .output()
+        .context("Failed to run git; is it installed?")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Cannot read the tree of {}: {}",
+            rev,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let mut sizes = BTreeMap::new();
+    for record in output.stdout.split(|byte| *byte == 0) {
+        if record.is_empty() {
+            continue;
+        }
+        // Each record is "<mode> <type> <object> <size>\t<path>".
+        let record = String::from_utf8_lossy(record);
+        let Some((info, path)) = record.split_once('\t') else {
+            anyhow::bail!("Unexpected git ls-tree output: {}", record);
+        };
+        let mut fields = info.split_whitespace();
+        if fields.nth(1) != Some("blob") {
+            continue;
+        }
+        let size = fields
+            .nth(1)
+            .and_then(|size| size.parse().ok())
+            .with_context(|| format!("Unexpected git ls-tree output: {}", record))?;
+        sizes.insert(path.to_string(), size);
+    }
+    Ok(sizes)
+}
@@ -0,0 +1,294 @@
+use clap::ValueEnum;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+
+use crate::FileEntry;
+use crate::ext::ExtensionRules;
+use crate::mounts;
+use crate::owner;
+use crate::period::Period;
+
+pub struct Group {
+    pub key: String,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+#[derive(Clone, Copy)]
+pub enum GroupBy {
+    Extension,
+    Dir(usize),
+    Owner,
+    /// The filesystem, labelled with its mount point.
+    Mount,
+    /// The calendar period of the modification time, in UTC when `utc` is set.
+    Period {
+        period: Period,
+        utc: bool,
+    },
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum GroupSort {
+    #[default]
+    Size,
+    Count,
+    Name,
+}
+
+impl GroupBy {
+    pub fn title(self) -> &'static str {
+        match self {
+            GroupBy::Extension => "By extension",
+            GroupBy::Dir(_) => "By directory",
+            GroupBy::Owner => "By owner",
+            GroupBy::Mount => "By mount",
+            GroupBy::Period { .. } => "By modification period",
+        }
+    }
+
+    /// The name used for this grouping in machine-readable output.
+    pub fn name(self) -> &'static str {
+        match self {
+            GroupBy::Extension => "extension",
+            GroupBy::Dir(_) => "directory",
+            GroupBy::Owner => "owner",
+            GroupBy::Mount => "mount",
+            GroupBy::Period { .. } => "period",
+        }
+    }
+
+    fn key(self, entry: &FileEntry, rules: &ExtensionRules) -> String {
+        let path = entry.path.as_path();
+        match self {
+            GroupBy::Extension => entry
+                .sniffed_ext
+                .map(str::to_string)
+                .or_else(|| rules.extension(path))
+                .unwrap_or_else(|| "<none>".to_string()),
+            GroupBy::Dir(depth) => {
+                let dir: PathBuf = path
+                    .parent()
+                    .into_iter()
+                    .flat_map(Path::components)
+                    .filter(|component| {
+                        !matches!(
+                            component,
+                            Component::CurDir | Component::RootDir | Component::Prefix(_)
+                        )
+                    })
+                    .take(depth)
+                    .collect();
+                if dir.as_os_str().is_empty() {
+                    ".".to_string()
+                } else {
+                    dir.display().to_string()
+                }
+            }
+            GroupBy::Owner => entry
+                .uid
+                .map(|uid| uid.to_string())
+                .unwrap_or_else(|| "<unknown>".to_string()),
+            GroupBy::Mount => entry
+                .device
+                .map(|device| device.to_string())
+                .unwrap_or_else(|| "<unknown>".to_string()),
+            GroupBy::Period { period, utc } => entry
+                .mtime
+                .map(|mtime| period.label(mtime, utc))
+                .unwrap_or_else(|| "<unknown>".to_string()),
+        }
+    }
+}
+
+pub fn group(entries: &[FileEntry], by: GroupBy, rules: &ExtensionRules) -> Vec<Group> {
+    let mut groups: HashMap<String, Group> = HashMap::new();
+
+    for entry in entries {
+        let key = by.key(entry, rules);
+        let group = groups.entry(key.clone()).or_insert(Group {
+            key,
+            count: 0,
+            bytes: 0,
+        });
+        group.count += 1;
+        group.bytes += entry.size;
+    }
+
+    let mut groups: Vec<Group> = groups.into_values().collect();
+    match by {
+        GroupBy::Owner => {
+            for group in &mut groups {
+                if let Ok(uid) = group.key.parse() {
+                    group.key = owner::user_name(uid);
+                }
+            }
+        }
+        GroupBy::Mount => {
+            let points = mounts::mount_points();
+            for group in &mut groups {
+                if let Ok(device) = group.key.parse() {
+                    group.key = mounts::label(device, &points);
+                }
+            }
+        }
+        _ => {}
+    }
+    groups
+}
+
+/// Splits the entries into one list per group key, ordered by key.
+pub fn partition(
+    entries: &[FileEntry],
+    by: GroupBy,
+    rules: &ExtensionRules,
+) -> BTreeMap<String, Vec<FileEntry>> {
+    let mut parts: BTreeMap<String, Vec<FileEntry>> = BTreeMap::new();
+    for entry in entries {
+        parts
+            .entry(by.key(entry, rules))
+            .or_default()
+            .push(entry.clone());
+    }
+    parts
+}
+
+/// Removes all but the `top` largest groups and any group smaller than
+/// `min_bytes`, returning them combined into a single `(other)` group.
+pub fn fold_small(
+    groups: &mut Vec<Group>,
+    top: Option<usize>,
+    min_bytes: Option<u64>,
+) -> Option<Group> {
+    sort_groups(groups, GroupSort::Size, false);
+    let keep = groups
+        .iter()
+        .take(top.unwrap_or(usize::MAX))
+        .take_while(|group| group.bytes >= min_bytes.unwrap_or(0))
+        .count();
+
+    let folded = groups.split_off(keep);
+    if folded.is_empty() {
+        return None;
+    }
+    Some(Group {
+        key: "(other)".to_string(),
+        count: folded.iter().map(|group| group.count).sum(),
+        bytes: folded.iter().map(|group| group.bytes).sum(),
+    })
+}
+
+/// Orders groups by the chosen key: size and count descending, name ascending.
+/// Ties are broken by name so repeated runs print groups in the same order.
+/// `reverse` flips whichever order was chosen.
+pub fn sort_groups(groups: &mut [Group], sort: GroupSort, reverse: bool) {
+    match sort {
+        GroupSort::Size => groups.sort_by(|a, b| {
+            Reverse(a.bytes)
+                .cmp(&Reverse(b.bytes))
+                .then_with(|| a.key.cmp(&b.key))
+        }),
+        GroupSort::Count => groups.sort_by(|a, b| {
+            Reverse(a.count)
+                .cmp(&Reverse(b.count))
+                .then_with(|| a.key.cmp(&b.key))
+        }),
+        GroupSort::Name => groups.sort_by(|a, b| a.key.cmp(&b.key)),
+    }
+    if reverse {
+        groups.reverse();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir_key(path: &str, depth: usize) -> String {
+        let entry = FileEntry::recorded(PathBuf::from(path), 0);
+        GroupBy::Dir(depth).key(&entry, &ExtensionRules::default())
+    }
+
+    #[test]
+    fn dir_key_skips_current_and_root_dirs() {
+        assert_eq!(dir_key("./src/a/b.rs", 1), "src");
+        assert_eq!(dir_key("/var/log/syslog", 1), "var");
+        assert_eq!(dir_key("/var/log/syslog", 2), "var/log");
+        assert_eq!(dir_key("top.txt", 1), ".");
+        assert_eq!(dir_key("/top.txt", 1), ".");
+    }
+
+    fn sample() -> Vec<FileEntry> {
+        // Several extensions tie on size and on count.
+        [
+            ("a.png", 10),
+            ("b.jpg", 10),
+            ("c.gif", 10),
+            ("d.txt", 4),
+            ("e.txt", 6),
+            ("f.rs", 30),
+            ("g.md", 1),
+            ("h.md", 2),
+            ("i.md", 7),
+        ]
+        .into_iter()
+        .map(|(path, size)| FileEntry::recorded(PathBuf::from(path), size))
+        .collect()
+    }
+
+    fn sorted_keys(sort: GroupSort, reverse: bool) -> Vec<String> {
+        let mut groups = group(&sample(), GroupBy::Extension, &ExtensionRules::default());
+        sort_groups(&mut groups, sort, reverse);
+        groups.into_iter().map(|group| group.key).collect()
+    }
+
+    #[test]
+    fn groups_sort_by_size_then_name() {
+        assert_eq!(
+            sorted_keys(GroupSort::Size, false),
+            ["rs", "gif", "jpg", "md", "png", "txt"]
+        );
+    }
+
+    #[test]
+    fn groups_sort_by_count_then_name() {
+        assert_eq!(
+            sorted_keys(GroupSort::Count, false),
+            ["md", "txt", "gif", "jpg", "png", "rs"]
+        );
+    }
+
+    #[test]
+    fn groups_sort_by_name_and_reverse() {
+        assert_eq!(
+            sorted_keys(GroupSort::Name, false),
+            ["gif", "jpg", "md", "png", "rs", "txt"]
+        );
+        assert_eq!(
+            sorted_keys(GroupSort::Name, true),
+            ["txt", "rs", "png", "md", "jpg", "gif"]
+        );
+    }
+
+    #[test]
+    fn group_order_does_not_depend_on_hash_order() {
+        // Every `group` call builds a HashMap with a fresh random seed.
+        let first = sorted_keys(GroupSort::Size, false);
+        for _ in 0..20 {
+            assert_eq!(sorted_keys(GroupSort::Size, false), first);
+        }
+    }
+
+    #[test]
+    fn folded_groups_are_combined() {
+        let mut groups = group(&sample(), GroupBy::Extension, &ExtensionRules::default());
+        let other = fold_small(&mut groups, Some(2), None).unwrap();
+        let kept: Vec<&str> = groups.iter().map(|group| group.key.as_str()).collect();
+        assert_eq!(kept, ["rs", "gif"]);
+        assert_eq!(
+            (other.key.as_str(), other.count, other.bytes),
+            ("(other)", 7, 40)
+        );
+    }
+}
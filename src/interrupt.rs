@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+use signal_hook::consts::SIGINT;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Tracks whether the user pressed Ctrl-C or the `--timeout` deadline
+/// passed. The first press only sets the flag, so the scan can stop and
+/// report what it measured so far; a second press exits immediately.
+pub struct Interrupt {
+    flag: Arc<AtomicBool>,
+    timed_out: Arc<AtomicBool>,
+}
+
+impl Interrupt {
+    pub fn install() -> Result<Self> {
+        let flag = Arc::new(AtomicBool::new(false));
+        signal_hook::flag::register_conditional_shutdown(SIGINT, 130, Arc::clone(&flag))
+            .context("Failed to install Ctrl-C handler")?;
+        signal_hook::flag::register(SIGINT, Arc::clone(&flag))
+            .context("Failed to install Ctrl-C handler")?;
+        Ok(Self {
+            flag,
+            timed_out: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Sets the flag from a watcher thread once `limit` has passed, as if
+    /// Ctrl-C had been pressed.
+    pub fn set_deadline(&self, limit: Duration) {
+        let flag = Arc::clone(&self.flag);
+        let timed_out = Arc::clone(&self.timed_out);
+        std::thread::spawn(move || {
+            std::thread::sleep(limit);
+            timed_out.store(true, Ordering::Relaxed);
+            flag.store(true, Ordering::Relaxed);
+        });
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    /// Whether the flag was set by the deadline rather than by Ctrl-C.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,145 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Windows rejects paths longer than `MAX_PATH` unless they use the verbatim
+/// `\\?\` (or `\\?\UNC\` for network shares) form. Returns the path to hand to
+/// filesystem calls; on other platforms this is always the path itself.
+#[cfg(windows)]
+pub fn fs_path(path: &Path) -> Cow<'_, Path> {
+    use std::ffi::OsString;
+    use std::path::{Component, PathBuf, Prefix};
+
+    const MAX_PATH: usize = 260;
+
+    if path.as_os_str().len() < MAX_PATH {
+        return Cow::Borrowed(path);
+    }
+
+    let Ok(absolute) = std::path::absolute(path) else {
+        return Cow::Borrowed(path);
+    };
+    let mut components = absolute.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return Cow::Borrowed(path);
+    };
+
+    // Built from the OS string, so names that are not valid Unicode survive.
+    let mut verbatim = OsString::with_capacity(absolute.as_os_str().len() + 8);
+    match prefix.kind() {
+        Prefix::Disk(_) => {
+            verbatim.push(r"\\?\");
+            verbatim.push(absolute.as_os_str());
+        }
+        Prefix::UNC(server, share) => {
+            verbatim.push(r"\\?\UNC\");
+            verbatim.push(server);
+            verbatim.push(r"\");
+            verbatim.push(share);
+            for component in components {
+                if let Component::Normal(name) = component {
+                    verbatim.push(r"\");
+                    verbatim.push(name);
+                }
+            }
+        }
+        // Already verbatim, or a device path that must not be rewritten.
+        _ => return Cow::Borrowed(path),
+    }
+    Cow::Owned(PathBuf::from(verbatim))
+}
+
+#[cfg(not(windows))]
+pub fn fs_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_paths_are_unchanged() {
+        let path = Path::new("src/main.rs");
+        assert!(matches!(fs_path(path), Cow::Borrowed(borrowed) if borrowed == path));
+    }
+
+    #[cfg(windows)]
+    fn long_tail() -> String {
+        vec!["directory"; 40].join(r"\")
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_drive_paths_become_verbatim() {
+        let raw = format!(r"C:\{}\file.txt", long_tail());
+        let converted = fs_path(Path::new(&raw));
+        assert_eq!(converted.as_os_str(), format!(r"\\?\{}", raw).as_str());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_unc_paths_become_verbatim_unc() {
+        let raw = format!(r"\\server\share\{}\file.txt", long_tail());
+        let converted = fs_path(Path::new(&raw));
+        assert_eq!(
+            converted.as_os_str(),
+            format!(r"\\?\UNC\server\share\{}\file.txt", long_tail()).as_str()
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_paths_below_a_unc_glob_root_become_verbatim_unc() {
+        // A glob root spelled with forward slashes is still a UNC share.
+        let raw = format!("//server/share/{}/file.txt", long_tail().replace('\\', "/"));
+        let converted = fs_path(Path::new(&raw));
+        assert_eq!(
+            converted.as_os_str(),
+            format!(r"\\?\UNC\server\share\{}\file.txt", long_tail()).as_str()
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn paths_that_are_not_unicode_keep_their_units() {
+        use std::ffi::OsString;
+        use std::os::windows::ffi::{OsStrExt, OsStringExt};
+
+        // An unpaired surrogate, which `to_string_lossy` would replace.
+        let mut wide: Vec<u16> = format!(r"C:\{}\", long_tail()).encode_utf16().collect();
+        wide.push(0xD800);
+        let raw = OsString::from_wide(&wide);
+        let converted = fs_path(Path::new(&raw));
+        assert_eq!(converted.as_os_str().encode_wide().last(), Some(0xD800));
+        assert!(
+            converted
+                .as_os_str()
+                .encode_wide()
+                .take(4)
+                .eq(r"\\?\".encode_utf16())
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn verbatim_paths_are_unchanged() {
+        let raw = format!(r"\\?\C:\{}\file.txt", long_tail());
+        assert!(matches!(fs_path(Path::new(&raw)), Cow::Borrowed(_)));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn files_beyond_max_path_can_be_measured() {
+        let root = tempfile::tempdir().unwrap();
+        let mut dir = root.path().to_path_buf();
+        while dir.as_os_str().len() < 300 {
+            dir.push("a_fairly_long_directory_name");
+        }
+        std::fs::create_dir_all(fs_path(&dir)).unwrap();
+        let file = dir.join("file.bin");
+        std::fs::write(fs_path(&file), [0u8; 1234]).unwrap();
+
+        assert!(file.as_os_str().len() > 260);
+        assert_eq!(std::fs::metadata(fs_path(&file)).unwrap().len(), 1234);
+    }
+}
@@ -0,0 +1,50 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Leading bytes of well-known formats and the extension each stands for.
+/// The offset is where the signature starts.
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "png"),
+    (0, b"\xff\xd8\xff", "jpg"),
+    (0, b"GIF87a", "gif"),
+    (0, b"GIF89a", "gif"),
+    (0, b"DDS ", "dds"),
+    (0, b"8BPS", "psd"),
+    (0, b"II*\0", "tif"),
+    (0, b"MM\0*", "tif"),
+    (8, b"WEBP", "webp"),
+    (8, b"WAVE", "wav"),
+    (4, b"ftyp", "mp4"),
+    (0, b"ID3", "mp3"),
+    (0, b"OggS", "ogg"),
+    (0, b"fLaC", "flac"),
+    (0, b"%PDF-", "pdf"),
+    (0, b"PK\x03\x04", "zip"),
+    (0, b"\x1f\x8b", "gz"),
+    (0, b"BZh", "bz2"),
+    (0, b"\xfd7zXZ\0", "xz"),
+    (0, b"7z\xbc\xaf\x27\x1c", "7z"),
+    (0, b"\x28\xb5\x2f\xfd", "zst"),
+    (0, b"SQLite format 3\0", "sqlite"),
+    (0, b"\0asm", "wasm"),
+    (0, b"\x7fELF", "elf"),
+];
+
+/// The extension matching the file's magic number, if it is a known format.
+/// Reads only the first few bytes; unreadable files have no match.
+pub fn sniff(path: &Path) -> Option<&'static str> {
+    let mut head = Vec::with_capacity(16);
+    File::open(crate::longpath::fs_path(path))
+        .ok()?
+        .take(16)
+        .read_to_end(&mut head)
+        .ok()?;
+
+    SIGNATURES
+        .iter()
+        .find(|(offset, signature, _)| {
+            head.get(*offset..offset + signature.len()) == Some(*signature)
+        })
+        .map(|(_, _, ext)| *ext)
+}
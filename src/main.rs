@@ -1,48 +1,729 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{ArgGroup, Parser};
 use colored::*;
 use glob::glob;
 use rayon::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Instant, SystemTime};
+
+use theme::Themed;
+
+/// Exit code used when `--min-free` finds the destination would run too low.
+const EXIT_NOT_ENOUGH_SPACE: i32 = 3;
+/// Exit code used when `--abort-after-errors` stops the scan.
+const EXIT_TOO_MANY_ERRORS: i32 = 4;
+/// Exit code used when the total is outside `--expect-total` and its tolerance.
+const EXIT_UNEXPECTED_TOTAL: i32 = 5;
+/// Exit code used after printing partial results for an interrupted scan.
+const EXIT_INTERRUPTED: i32 = 130;
+/// Exit code used after printing partial results for a scan that hit `--timeout`.
+const EXIT_TIMED_OUT: i32 = 124;
+
+mod buckets;
+mod checksum;
+mod collate;
+mod compress;
+mod dedup;
+mod display;
+mod encoding;
+mod exclude;
+mod ext;
+mod filter;
+mod git;
+mod groups;
+mod interrupt;
+mod longpath;
+mod magic;
+mod merge;
+mod mounts;
+mod names;
+mod neardup;
+mod output;
+mod owner;
+mod patterns;
+mod period;
+mod profile;
+mod progress;
+mod report;
+mod rollup;
+mod size;
+mod snapshot;
+mod sort;
+mod sqlite;
+mod stats;
+mod theme;
+mod throttle;
+mod timefilter;
+mod treemap;
+mod tui;
+mod verify;
+mod walk;
+mod weights;
 
 #[derive(Parser)]
 #[command(name = "weight")]
 #[command(about = "Calculate total size of files matching glob patterns")]
 #[command(version = "1.0")]
 #[command(
-    after_help = "EXAMPLES:\n  weight **/*.png **/*.jpg **/*.dds\n  weight -v *.png\n  weight --threads 4 **/*.rs\n\nNOTE: In Nushell, use separate patterns instead of brace expansion"
+    after_help = "EXAMPLES:\n  weight **/*.png **/*.jpg **/*.dds\n  weight -v *.png\n  weight --threads 4 **/*.rs\n\nNOTE: Brace groups like **/*.{png,jpg} are expanded by weight itself; quote them so your shell passes them through"
 )]
+#[command(group(ArgGroup::new("format").args(["json", "csv"])))]
+#[command(group(ArgGroup::new("dedup").args(["canonical_dedup", "aggregate_symlinks"]).multiple(true)))]
 struct Args {
-    #[arg(required = true)]
+    #[arg(required_unless_present_any = ["stdin", "merge"])]
     patterns: Vec<String>,
+    /// Read more patterns from stdin, one per line: glob patterns, or literal paths
+    #[arg(long, value_enum, value_name = "MODE")]
+    stdin: Option<patterns::StdinMode>,
+    /// Expand patterns and show relative paths as if run from DIR. File options such as
+    /// --output or --since still resolve against the real working directory
+    #[arg(long, value_name = "DIR")]
+    root: Option<PathBuf>,
+    /// Stop after DURATION (e.g. 90s, 5m) of wall-clock time for the whole run, print the
+    /// partial results labelled as incomplete and exit with code 124
+    #[arg(long, value_name = "DURATION", alias = "scan-timeout", value_parser = timefilter::parse_duration)]
+    timeout: Option<std::time::Duration>,
+    /// When stdout is not a terminal, log "processed N of M files, X so far" to stderr every
+    /// DURATION (e.g. 30s) while measuring, so CI logs show the scan is alive
+    #[arg(long, value_name = "DURATION", value_parser = timefilter::parse_duration)]
+    progress_interval: Option<std::time::Duration>,
+    /// Number of worker threads, which bounds CPU work such as hashing
     #[arg(short, long)]
     threads: Option<usize>,
+    /// Allow at most N filesystem metadata calls in flight at once, whatever the number of
+    /// threads. Network filesystems such as NFS are often faster with 8-16
+    #[arg(long, value_name = "N")]
+    io_concurrency: Option<std::num::NonZeroUsize>,
     #[arg(short, long)]
     verbose: bool,
     #[arg(short, long)]
     debug: bool,
+    /// Also print every ancestor directory with the cumulative size of the files it contains
+    #[arg(long)]
+    parents: bool,
+    /// Print the N directories with the largest cumulative size, at any depth, largest first
+    #[arg(long, value_name = "N")]
+    top_dirs: Option<usize>,
+    /// Print only the directory with the largest cumulative size as "<path>\t<bytes>", the
+    /// one-line form of --top-dirs 1 for scripts. Prints nothing when no file is in a directory
+    #[arg(
+        long,
+        alias = "report-largest-dir",
+        conflicts_with_all = ["raw", "verbose", "debug", "json_stream", "dry_run", "list", "since", "top_dirs"]
+    )]
+    largest_dir: bool,
+    /// Hash each file's contents and show the digest next to its size (reads every file)
+    #[arg(long, value_enum, value_name = "ALGO")]
+    checksum: Option<checksum::Algorithm>,
+    /// Estimate how well the files would compress, overall and per extension, by compressing
+    /// the first 64 KB of each at gzip level 1 and extrapolating (reads every file)
+    #[arg(long)]
+    estimate_compressed: bool,
+    /// Exit with an error if fewer than N files matched
+    #[arg(long, value_name = "N")]
+    min_count: Option<usize>,
+    /// Exit with an error if no files matched (same as --min-count 1)
+    #[arg(long)]
+    fail_on_empty: bool,
+    /// Show listed paths relative to this directory
+    #[arg(long, value_name = "PATH", conflicts_with = "relative_to_pattern_root")]
+    strip_prefix: Option<PathBuf>,
+    /// Show listed paths relative to the literal (non-glob) root of their pattern
+    #[arg(long)]
+    relative_to_pattern_root: bool,
+    /// Show paths exactly as the glob expansion produced them, so they can be fed back
+    /// through --stdin paths. Paths that are not valid UTF-8 can only be reproduced
+    /// byte for byte with --list
+    #[arg(long, conflicts_with_all = ["strip_prefix", "relative_to_pattern_root", "encoding"])]
+    raw_paths: bool,
+    /// Print one JSON object per file as soon as it is measured, then a summary object.
+    /// Lines are emitted in completion order, which varies between runs
+    #[arg(long)]
+    json_stream: bool,
+    /// Flush stdout after every per-file line, so a slow consumer in a pipe, e.g. a
+    /// `while read` loop on --json-stream, sees each result at once. Costs some throughput
+    #[arg(long, alias = "line-buffered")]
+    flush_each: bool,
+    /// Skip directories that cannot be read without a warning for each, and report how many were skipped
+    #[arg(long)]
+    ignore_unreadable: bool,
+    /// Fail instead of warning when a path cannot be read while expanding the patterns, e.g.
+    /// a directory that became unreadable mid-walk, so an incomplete scan never yields a total
+    #[arg(
+        long,
+        alias = "glob-error-strict",
+        conflicts_with = "ignore_unreadable"
+    )]
+    strict_glob: bool,
+    /// List the files that would be measured without reading their sizes
+    #[arg(long)]
+    dry_run: bool,
+    /// Break the total down by file extension
+    #[arg(long, conflicts_with_all = ["group_by_dir", "by_owner", "by_period", "by_mount"])]
+    by_ext: bool,
+    /// Break the total down by directory, truncated to DEPTH components
+    #[arg(long, value_name = "DEPTH", conflicts_with_all = ["by_owner", "by_period", "by_mount"])]
+    group_by_dir: Option<usize>,
+    /// Break the total down by the user owning each file (Unix only)
+    #[arg(long, conflicts_with_all = ["by_period", "by_mount"])]
+    by_owner: bool,
+    /// Break the total down by the filesystem each file is on, labelled with its mount point,
+    /// to spot a scan crossing into another mount (Unix only)
+    #[arg(long, alias = "split-by-mount", conflicts_with = "by_period")]
+    by_mount: bool,
+    /// Break the total down by the calendar period each file was last modified in,
+    /// listed chronologically
+    #[arg(long, value_enum, value_name = "PERIOD")]
+    by_period: Option<period::Period>,
+    /// With --by-period, use UTC instead of local time for period boundaries
+    #[arg(long, requires = "by_period")]
+    utc: bool,
+    /// Sort files into size tiers split at the given boundaries, e.g. 1MB,100MB,1GB
+    #[arg(long, value_name = "SIZES", value_parser = buckets::parse_boundaries)]
+    bucket_by: Option<buckets::Boundaries>,
+    /// Highlight groups larger than SIZE in red
+    #[arg(long, value_name = "SIZE", value_parser = size::parse_size)]
+    group_warn_over: Option<u64>,
+    /// Order groups by total size (default), file count or name. --by-period groups are
+    /// ordered by name, which is chronological, unless this is given
+    #[arg(long, value_enum)]
+    group_sort: Option<groups::GroupSort>,
+    /// Reverse the group order
+    #[arg(long)]
+    group_reverse: bool,
+    /// Keep only the N largest groups and fold the rest into a single "(other)" group
+    #[arg(long, value_name = "N")]
+    top_groups: Option<usize>,
+    /// Fold groups smaller than SIZE into a single "(other)" group
+    #[arg(long, value_name = "SIZE", value_parser = size::parse_size)]
+    min_group_bytes: Option<u64>,
+    /// Stop measuring once N files have failed, e.g. on an unavailable network mount,
+    /// and exit with code 4
+    #[arg(long, alias = "skip-errors-over", value_name = "N")]
+    abort_after_errors: Option<usize>,
+    /// Skip paths given literally that do not exist, or files that vanish before they are
+    /// measured, instead of reporting an error for each
+    #[arg(long)]
+    allow_missing: bool,
+    /// Report how many matched files have more than one hard link, and how many distinct
+    /// files they are, without changing the total (Unix only)
+    #[arg(long, alias = "count-hardlinks")]
+    hardlink_stats: bool,
+    /// Count the target of several symlinks pointing at the same file only once
+    #[arg(long)]
+    aggregate_symlinks: bool,
+    /// Decimal separator for human-readable sizes; defaults to the one used by your locale
+    #[arg(long, value_name = "SEP", value_parser = size::parse_decimal_sep)]
+    decimal_sep: Option<char>,
+    /// Only count files last accessed before TIME (an age like 30d, or a date like 2024-01-31).
+    /// Access times are unreliable on filesystems mounted with noatime
+    #[arg(long, value_name = "TIME", value_parser = timefilter::parse_time_point)]
+    accessed_before: Option<SystemTime>,
+    /// Only count files last accessed at or after TIME
+    #[arg(long, value_name = "TIME", value_parser = timefilter::parse_time_point)]
+    accessed_after: Option<SystemTime>,
+    /// Only count files created before TIME
+    #[arg(long, value_name = "TIME", value_parser = timefilter::parse_time_point)]
+    created_before: Option<SystemTime>,
+    /// Only count files created at or after TIME
+    #[arg(long, value_name = "TIME", value_parser = timefilter::parse_time_point)]
+    created_after: Option<SystemTime>,
+    /// Print only the total size in bytes as a single integer line. This output contract is
+    /// stable: everything else, including warnings and errors, goes to stderr
+    #[arg(
+        long,
+        alias = "total-only-bytes",
+        conflicts_with_all = ["verbose", "debug", "json_stream", "dry_run", "parents", "by_ext", "group_by_dir", "by_period"]
+    )]
+    raw: bool,
+    /// Only expand the patterns and print the matching paths, without measuring anything.
+    /// Paths follow the order of the patterns, then the order each pattern matched them in
+    #[arg(long)]
+    list: bool,
+    /// With --list, separate paths with NUL instead of newline
+    #[arg(short = '0', long = "null", requires = "list")]
+    null: bool,
+    /// With --list, print only regular files
+    #[arg(long, requires = "list")]
+    files_only: bool,
+    /// Print only the net size change since the snapshot in FILE
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["raw", "json_stream", "dry_run", "list"])]
+    since: Option<PathBuf>,
+    /// With --since, exit with an error if the growth exceeds SIZE
+    #[arg(long, value_name = "SIZE", value_parser = size::parse_size, requires = "since")]
+    alert_over: Option<u64>,
+    /// Exit with code 5 unless the total size is SIZE, give or take --tolerance, so CI
+    /// catches unexpected shrinkage as well as growth
+    #[arg(long, value_name = "SIZE", alias = "size-delta-exit", value_parser = size::parse_size)]
+    expect_total: Option<u64>,
+    /// With --expect-total, how far the total may be off either way: a size like 512KB or a
+    /// percentage of the expected size like 10%. Defaults to an exact match
+    #[arg(long, value_name = "SIZE|PERCENT", value_parser = size::parse_tolerance, requires = "expect_total")]
+    tolerance: Option<size::Tolerance>,
+    /// With --since, report changes as snapshot minus current scan instead of current scan
+    /// minus snapshot, so the current scan is the baseline
+    #[arg(long, requires = "since")]
+    reverse_diff: bool,
+    /// With --since, list every file whose size changed by more than SIZE
+    #[arg(long, value_name = "SIZE", value_parser = size::parse_size, requires = "since")]
+    diff_threshold: Option<u64>,
+    /// With --since, replace the snapshot with the current results (creating it if missing).
+    /// Skipped when the scan was interrupted or timed out
+    #[arg(long, requires = "since")]
+    update_snapshot: bool,
+    /// Also report the total with each file's size rounded up to a multiple of SIZE, e.g. a
+    /// 4KB cluster or 64KB object-store chunk, and the overhead that adds
+    #[arg(long, value_name = "SIZE", value_parser = size::parse_unit)]
+    round_up_to: Option<u64>,
+    /// Also report a weighted total, multiplying each file's size by a per-extension cost
+    /// from FILE, one ext=multiplier per line (e.g. dds=4). Unlisted extensions count at 1
+    #[arg(long, value_name = "FILE")]
+    weights: Option<PathBuf>,
+    /// Combine the snapshots instead of scanning: files recorded with the same size are
+    /// counted once, and paths whose sizes differ are counted per version and listed as conflicts.
+    /// Only the summary, --verbose, grouping, --raw, --json, --csv and --output reports apply
+    #[arg(
+        long,
+        value_name = "SNAPSHOT",
+        num_args = 1..,
+        conflicts_with_all = [
+            "patterns", "stdin", "since", "list", "dry_run", "json_stream", "interactive",
+            "sqlite", "treemap_svg", "shard_output", "parents", "top_dirs", "largest_dir",
+            "bucket_by", "count_names", "hardlink_stats", "extensionless", "near_dup",
+            "estimate_compressed", "weights", "ext_case_report", "dedup_report",
+            "pattern_report", "vs_free", "round_up_to", "expect_total",
+        ]
+    )]
+    merge: Vec<PathBuf>,
+    /// Compare the sizes of matched files between two git revisions instead of scanning,
+    /// listing files added, removed or resized and the total delta. Sizes are read from
+    /// the repository, so neither revision is checked out
+    #[arg(
+        long,
+        alias = "compare-git-refs",
+        num_args = 2,
+        value_names = ["REF1", "REF2"],
+        conflicts_with_all = ["merge", "stdin", "since", "list", "dry_run", "json_stream", "interactive", "raw", "format"]
+    )]
+    git_diff: Vec<String>,
+    /// With --merge, prefix every path with its snapshot's file name instead of merging
+    /// equal paths, e.g. host-a:src/main.rs
+    #[arg(long, requires = "merge")]
+    merge_qualify: bool,
+    /// Only count files tracked by git (run inside a git repository)
+    #[arg(long, alias = "follow-git")]
+    git_tracked: bool,
+    /// Only count files owned by USER, given as a name or numeric uid (Unix only)
+    #[arg(long, value_name = "USER")]
+    user: Option<String>,
+    /// Let wildcards match hidden files and directories whose names start with a dot,
+    /// like .env. By default only a pattern that spells out the dot matches them, and `**`
+    /// does not descend into hidden directories at all
+    #[arg(long, alias = "match-dotfiles")]
+    hidden: bool,
+    /// Never descend more than N directories below each pattern's literal root.
+    /// Deeper directories are not visited at all
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+    /// Never descend into symlinked directories while expanding wildcards, like du. The
+    /// link itself is counted at its own size, but nothing under its target is
+    #[arg(long)]
+    skip_symlinked_dirs: bool,
+    /// Only count files at least N components below the current directory, after the patterns
+    /// are expanded: a.txt is at depth 1, src/a.txt at depth 2. Also applies to --stdin paths
+    #[arg(long, value_name = "N")]
+    min_path_depth: Option<usize>,
+    /// Only count files at most N components below the current directory, counted as for
+    /// --min-path-depth. Unlike --max-depth this filters matches instead of limiting the walk
+    #[arg(long, value_name = "N")]
+    max_path_depth: Option<usize>,
+    /// Expand all patterns with one shared directory walk instead of one walk per pattern,
+    /// so patterns under the same root (e.g. several src/** globs) read each directory once
+    #[arg(long)]
+    parallel_glob_once: bool,
+    /// Exit with an error if any pattern matches no files, like bash's failglob
+    #[arg(long)]
+    fail_glob: bool,
+    /// Also report the bytes taken by file names, kept apart from the content total. Counts
+    /// each file's name, or with =path its whole path as matched
+    #[arg(long, value_enum, value_name = "PART", num_args = 0..=1, require_equals = true, default_missing_value = "name", alias = "size-of-names")]
+    count_names: Option<names::NameCost>,
+    /// Fold related extensions into one group, e.g. jpeg=jpg,yaml=yml.
+    /// Without a value, a built-in set of common aliases is used
+    #[arg(long, value_name = "MAPPINGS", num_args = 0..=1, default_missing_value = "default")]
+    merge_ext: Option<String>,
+    /// Compare the total against the free space of the filesystem containing PATH
+    #[arg(long, value_name = "PATH")]
+    vs_free: Option<PathBuf>,
+    /// With --vs-free, fail with exit code 3 unless at least SIZE would remain free
+    /// after copying the total there
+    #[arg(long, value_name = "SIZE", value_parser = size::parse_size, requires = "vs_free")]
+    min_free: Option<u64>,
+    /// How to write paths that are not valid UTF-8. In JSON, base64 applies only
+    /// to those paths, stored in a "path_b64" field instead of "path"; other
+    /// outputs base64-encode every path
+    #[arg(long, value_enum)]
+    encoding: Option<encoding::PathEncoding>,
+    /// Keep the full summary even when only a single file matches
+    #[arg(long)]
+    no_flatten_single: bool,
+    /// Order the --verbose file listing by size or by path
+    #[arg(long, value_enum)]
+    sort: Option<sort::FileSort>,
+    /// With --sort, compare paths by the collation rules of LOCALE (e.g. de_DE.UTF-8, or ""
+    /// for the environment's) instead of byte by byte. Slower; only the display order changes
+    #[arg(long, value_name = "LOCALE", requires = "sort")]
+    collate: Option<String>,
+    /// Leave out the "Found N files" line and the summary block, so --verbose prints only
+    /// the per-file lines
+    #[arg(long)]
+    no_summary: bool,
+    /// Show sizes as exact byte counts instead of KB/MB/GB
+    #[arg(long)]
+    bytes: bool,
+    /// Follow the human total and group totals with their exact byte count, e.g.
+    /// "1.50 GB (1610612736 bytes)"
+    #[arg(long, alias = "total-precision-bytes", conflicts_with = "bytes")]
+    show_exact: bool,
+    /// Divide sizes by 1000 or 1024 per unit step. Independent of the unit labels
+    #[arg(long, value_name = "BASE", value_parser = size::parse_base, default_value = "1024")]
+    bytes_base: u64,
+    /// Show SI sizes: divide by 1000 per unit step and label units KB, MB, GB.
+    /// Shorthand for --bytes-base 1000
+    #[arg(long, conflicts_with_all = ["binary", "bytes_base"])]
+    si: bool,
+    /// Label units KiB, MiB, GiB, whatever --bytes-base is
+    #[arg(long)]
+    binary: bool,
+    /// Pad the per-file size field to a fixed width of N characters
+    #[arg(long, value_name = "N")]
+    pad: Option<usize>,
+    /// Measure everything a second time with a simple sequential implementation and fail
+    /// unless it agrees with the parallel result. Slow; meant for CI and debugging
+    #[arg(long, alias = "verify-total")]
+    verify: bool,
+    /// Report how fast files were measured, as total size over the wall time of the metadata
+    /// stage (on stderr). This is stat throughput unless --checksum or
+    /// --estimate-compressed read the contents
+    #[arg(long, alias = "byte-rate")]
+    throughput: bool,
+    /// Report how the metadata work was spread across threads (on stderr)
+    #[arg(long)]
+    profile: bool,
+    /// Report what the scan touched: entries seen, directories, symlinks, files stat'd and
+    /// what was skipped at each stage (on stderr)
+    #[arg(long, alias = "follow-count")]
+    scan_stats: bool,
+    /// Only count files matching EXPR, e.g. "size > 1MB && mtime < 30d".
+    /// Fields: size, mtime, atime, ext, name. Operators: < <= > >= == != ~ (glob), && || ! ( )
+    #[arg(long, value_name = "EXPR", value_parser = filter::parse)]
+    filter: Option<filter::Filter>,
+    /// Skip files larger than SIZE; files of exactly SIZE are kept, so 0 counts only empty
+    /// files. Size limits and --filter combine with AND: a file must pass all of them
+    #[arg(long, value_name = "SIZE", value_parser = size::parse_size)]
+    exclude_larger_than: Option<u64>,
+    /// Skip files smaller than SIZE; files of exactly SIZE are kept. Combines with
+    /// --exclude-larger-than and --filter with AND
+    #[arg(long, value_name = "SIZE", value_parser = size::parse_size)]
+    exclude_smaller_than: Option<u64>,
+    /// Count each file once even when several matched paths lead to it, by comparing canonical paths
+    #[arg(long)]
+    canonical_dedup: bool,
+    /// Count a path matched by several overlapping patterns once, instead of once per pattern
+    /// that matched it
+    #[arg(long)]
+    dedup_overlaps: bool,
+    /// When grouping by extension, group files without an extension by the type their magic
+    /// number reveals, e.g. a PNG saved without an extension groups under png
+    #[arg(long, alias = "ext-alias")]
+    ext_from_magic: bool,
+    /// With --ext-from-magic, sniff every file and prefer the detected type over its
+    /// extension, so a .bin that is really a PNG groups under png
+    #[arg(long, requires = "ext_from_magic")]
+    force_magic: bool,
+    /// Treat compound extensions as a single extension, so a.tar.gz has extension tar.gz.
+    /// Takes an optional comma-separated list of suffixes such as
+    /// --compound-ext=tar.gz,d.ts; the default list is tar.gz, tar.bz2, tar.xz and tar.zst
+    #[arg(
+        long,
+        value_name = "LIST",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ext::DEFAULT_COMPOUNDS
+    )]
+    compound_ext: Option<String>,
+    /// Keep the case of extensions when filtering and grouping, so PNG and png differ.
+    /// By default extensions are lowercased
+    #[arg(long)]
+    case_sensitive_ext: bool,
+    /// List extensions written with more than one casing, such as png and PNG, and the
+    /// files using each casing
+    #[arg(long)]
+    ext_case_report: bool,
+    /// List groups of files that are probably duplicates: the same size and the same hash of
+    /// their first, middle and last 4KB. A heuristic that can report false positives; compare
+    /// full --checksum digests to be sure
+    #[arg(long, alias = "deduplicate-by-content-sample")]
+    near_dup: bool,
+    /// List the files that have no extension, largest first, with their count and total size
+    #[arg(long, alias = "extensionless-report")]
+    extensionless: bool,
+    /// After deduplication, list which paths were collapsed into which counted file and how
+    /// many bytes that saved compared with summing every path
+    #[arg(long, requires = "dedup")]
+    dedup_report: bool,
+    /// After deduplication, show for each pattern how many files it matched, how many no
+    /// other pattern matched, how many it shares, and how many it was first to match
+    #[arg(long, alias = "matched-patterns-summary")]
+    pattern_report: bool,
+    /// Skip files matching GLOB; may be repeated
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+    /// Skip files matching any glob listed in FILE, one per line; blank lines and # comments
+    /// are ignored
+    #[arg(long, value_name = "FILE")]
+    exclude_from: Option<PathBuf>,
+    /// Disable colored output (also disabled when NO_COLOR is set)
+    #[arg(long)]
+    no_color: bool,
+    /// Color palette for terminal output; mono uses only bold and underline
+    #[arg(
+        long,
+        value_enum,
+        value_name = "NAME",
+        alias = "color-theme",
+        default_value = "dark"
+    )]
+    theme: theme::ThemeName,
+    /// Produce byte-identical output across runs and machines: one thread, files listed by
+    /// name, no color, '.' as decimal separator, and report timestamps taken from
+    /// SOURCE_DATE_EPOCH (or the Unix epoch) instead of the clock
+    #[arg(long, conflicts_with_all = ["threads", "profile", "interactive"])]
+    deterministic: bool,
+    /// Write a JSON report with run metadata, totals and every file
+    #[arg(long, conflicts_with_all = ["csv", "raw", "json_stream", "since", "dry_run", "list"])]
+    json: bool,
+    /// Write JSON reports on a single line instead of indented. --json-stream is always
+    /// one compact object per line
+    #[arg(long)]
+    json_compact: bool,
+    /// Declare the size unit in --json and --csv reports: a "unit" field in JSON and a unit
+    /// column in CSV. Report sizes are always whole bytes, so the unit is always "bytes"
+    #[arg(long, requires = "format")]
+    units_in_header: bool,
+    /// Write a CSV report with one row per file
+    #[arg(long, conflicts_with_all = ["raw", "json_stream", "since", "dry_run", "list"])]
+    csv: bool,
+    /// Write the --json/--csv report to FILE instead of stdout. Without --json or --csv,
+    /// the format follows the extension of FILE: .json or .csv. Other extensions, such as
+    /// .tsv, .md or .html, are rejected
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["raw", "json_stream", "since", "dry_run", "list"])]
+    output: Option<PathBuf>,
+    /// Write one --json/--csv report per top-level directory into DIR, named after the
+    /// directory (_top for files directly in the current directory, and an extra _ in front of
+    /// names already starting with _), plus an _index file listing them. Fails if two
+    /// directories would get the same file name
+    #[arg(long, value_name = "DIR", requires = "format", conflicts_with_all = ["output", "append", "raw", "json_stream", "since", "dry_run", "list", "interactive"])]
+    shard_output: Option<PathBuf>,
+    /// Append to the --output file instead of replacing it. CSV rows then carry the run's
+    /// timestamp, version and patterns, and the header is only written to an empty file
+    #[arg(long, requires = "output")]
+    append: bool,
+    /// Also write every file into a `files(path, bytes, ext, mtime)` table of the SQLite
+    /// database FILE, replacing that table
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["dry_run", "list"])]
+    sqlite: Option<PathBuf>,
+    /// Also draw a treemap of the matched files, nested by directory with areas proportional
+    /// to size, into the standalone SVG file FILE
+    #[arg(long, value_name = "FILE", alias = "treemap", conflicts_with_all = ["dry_run", "list"])]
+    treemap_svg: Option<PathBuf>,
+    /// Browse the results in an interactive, sortable and filterable table
+    #[arg(long, conflicts_with_all = ["raw", "json_stream", "since", "dry_run", "list", "json", "csv", "output"])]
+    interactive: bool,
+}
+
+impl Args {
+    /// Makes the file options absolute, then changes into `--root` so patterns and
+    /// relative output paths are based there.
+    fn enter_root(&mut self) -> Result<()> {
+        let Some(root) = &self.root else {
+            return Ok(());
+        };
+        let files = self
+            .since
+            .iter_mut()
+            .chain(self.weights.iter_mut())
+            .chain(self.merge.iter_mut())
+            .chain(self.vs_free.iter_mut())
+            .chain(self.exclude_from.iter_mut())
+            .chain(self.output.iter_mut())
+            .chain(self.shard_output.iter_mut())
+            .chain(self.sqlite.iter_mut())
+            .chain(self.treemap_svg.iter_mut());
+        for file in files {
+            *file = std::path::absolute(&*file)
+                .with_context(|| format!("Failed to resolve path: {}", file.display()))?;
+        }
+        env::set_current_dir(root)
+            .with_context(|| format!("Cannot use root directory: {}", root.display()))
+    }
+
+    /// Pins down everything that could differ between runs or machines.
+    fn make_deterministic(&mut self) {
+        self.threads = Some(1);
+        self.sort.get_or_insert(sort::FileSort::Name);
+        self.decimal_sep.get_or_insert('.');
+        colored::control::set_override(false);
+    }
+
+    fn human_output(&self) -> bool {
+        let report_to_stdout =
+            self.report_format().is_some() && self.output.is_none() && self.shard_output.is_none();
+        !self.json_stream
+            && !self.raw
+            && !self.largest_dir
+            && self.since.is_none()
+            && !report_to_stdout
+    }
+
+    fn group_sort(&self) -> groups::GroupSort {
+        match (self.group_sort, self.by_period) {
+            (Some(sort), _) => sort,
+            (None, Some(_)) => groups::GroupSort::Name,
+            (None, None) => groups::GroupSort::default(),
+        }
+    }
+
+    /// Groups the entries by the active grouping, in display order. Groups
+    /// folded by `--top-groups` or `--min-group-bytes` come last as `(other)`.
+    fn groups(
+        &self,
+        entries: &[FileEntry],
+        rules: &ext::ExtensionRules,
+    ) -> Option<(groups::GroupBy, Vec<groups::Group>)> {
+        let group_by = self.group_by()?;
+        let mut groups = groups::group(entries, group_by, rules);
+        let other = groups::fold_small(&mut groups, self.top_groups, self.min_group_bytes);
+        groups::sort_groups(&mut groups, self.group_sort(), self.group_reverse);
+        groups.extend(other);
+        Some((group_by, groups))
+    }
+
+    fn report_format(&self) -> Option<report::Format> {
+        if self.json {
+            Some(report::Format::Json)
+        } else if self.csv {
+            Some(report::Format::Csv)
+        } else {
+            self.output.as_deref().and_then(report::Format::from_path)
+        }
+    }
+
+    fn group_by(&self) -> Option<groups::GroupBy> {
+        if self.by_ext {
+            Some(groups::GroupBy::Extension)
+        } else if self.by_owner && owner::supported() {
+            Some(groups::GroupBy::Owner)
+        } else if self.by_mount && mounts::supported() {
+            Some(groups::GroupBy::Mount)
+        } else if let Some(period) = self.by_period {
+            Some(groups::GroupBy::Period {
+                period,
+                utc: self.utc,
+            })
+        } else {
+            self.group_by_dir.map(groups::GroupBy::Dir)
+        }
+    }
+}
+
+#[derive(Clone)]
+struct FileEntry {
+    path: PathBuf,
+    size: u64,
+    checksum: Option<String>,
+    id: Option<dedup::FileId>,
+    is_symlink: bool,
+    uid: Option<u32>,
+    /// The device the file lives on, for --by-mount.
+    device: Option<u64>,
+    mtime: Option<SystemTime>,
+    compressed: Option<u64>,
+    /// Hard link count, with --hardlink-stats on Unix.
+    links: Option<u64>,
+    /// The extension implied by the file's magic number, with --ext-from-magic.
+    sniffed_ext: Option<&'static str>,
+}
+
+impl FileEntry {
+    /// An entry known only by its path and size, e.g. from a snapshot.
+    fn recorded(path: PathBuf, size: u64) -> Self {
+        Self {
+            path,
+            size,
+            checksum: None,
+            id: None,
+            is_symlink: false,
+            uid: None,
+            device: None,
+            mtime: None,
+            compressed: None,
+            links: None,
+            sniffed_ext: None,
+        }
+    }
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if args.deterministic {
+        args.make_deterministic();
+    }
+    if args.no_color {
+        colored::control::set_override(false);
+    }
+    theme::set(args.theme);
+    if let Some(output) = &args.output
+        && args.report_format().is_none()
+    {
+        if let Some(format) = report::Format::unsupported(output) {
+            anyhow::bail!(
+                "{} reports are not supported: {} needs a .json or .csv extension, or --json or --csv",
+                format,
+                output.display()
+            );
+        }
+        anyhow::bail!(
+            "Cannot tell the report format from the name of {}: only .json and .csv are \
+             recognized; add --json or --csv",
+            output.display()
+        );
+    }
+    args.enter_root()?;
+    if let Some(mode) = args.stdin {
+        args.patterns.extend(patterns::read_stdin(mode)?);
+    }
 
     if args.debug {
         println!(
             "{}: {}",
-            "Current directory".blue().bold(),
+            "Current directory".label().bold(),
             env::current_dir()
                 .map(|p| p.display().to_string())
                 .unwrap_or_else(|_| "Unable to get current dir".to_string())
-                .cyan()
+                .value()
         );
-        println!("{}: {:?}", "Arguments".blue().bold(), args.patterns);
+        println!("{}: {:?}", "Arguments".label().bold(), args.patterns);
 
         match std::fs::read_dir(".") {
-            Ok(_) => println!("{}: Current directory is readable", "✓".green()),
+            Ok(_) => println!("{}: Current directory is readable", "✓".good()),
             Err(e) => {
-                println!("{}: Cannot read current directory: {}", "✗".red(), e);
+                println!("{}: Cannot read current directory: {}", "✗".bad(), e);
                 return Err(anyhow::anyhow!("Cannot read current directory: {}", e));
             }
         }
@@ -55,197 +736,1869 @@ fn main() -> Result<()> {
             .context("Failed to set thread pool size")?;
     }
 
-    let all_candidate_paths = args.patterns.par_iter().map(|pattern| -> Result<_> {
+    let expanded_patterns: Vec<(usize, String)> = args
+        .patterns
+        .iter()
+        .enumerate()
+        .flat_map(|(index, pattern)| {
+            let expanded = patterns::expand_braces(pattern);
+            if args.debug && expanded.len() > 1 {
+                println!(
+                    "{}: {} -> {}",
+                    "Expanded braces".warn(),
+                    pattern.value(),
+                    expanded.join(" ").value()
+                );
+            }
+            expanded.into_iter().map(move |expanded| (index, expanded))
+        })
+        .collect();
+
+    let pattern_roots = if args.relative_to_pattern_root {
+        expanded_patterns
+            .iter()
+            .map(|(_, pattern)| display::pattern_root(pattern))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let path_display = if args.raw_paths {
+        display::PathDisplay::raw()
+    } else {
+        display::PathDisplay::new(
+            args.strip_prefix.clone(),
+            pattern_roots,
+            args.encoding.unwrap_or_default(),
+            args.debug,
+        )
+    };
+
+    let size_format = size::SizeFormat {
+        decimal_sep: args.decimal_sep.unwrap_or_else(size::locale_decimal_sep),
+        bytes: args.bytes,
+        pad: args.pad,
+        base: if args.si { 1000 } else { args.bytes_base },
+        binary_labels: args.binary,
+        exact: args.show_exact,
+    };
+
+    let collator = args
+        .collate
+        .as_deref()
+        .map(collate::Collator::new)
+        .transpose()?;
+    if let (Some(smaller), Some(larger)) = (args.exclude_smaller_than, args.exclude_larger_than)
+        && smaller > larger
+    {
+        anyhow::bail!(
+            "--exclude-smaller-than {} and --exclude-larger-than {} leave no size to count",
+            size_format.format(smaller),
+            size_format.format(larger)
+        );
+    }
+    if let (Some(min), Some(max)) = (args.min_path_depth, args.max_path_depth)
+        && min > max
+    {
+        anyhow::bail!(
+            "--min-path-depth {} is greater than --max-path-depth {}",
+            min,
+            max
+        );
+    }
+    if args.hardlink_stats && !cfg!(unix) {
+        eprintln!(
+            "{}: --hardlink-stats is only supported on Unix and will be ignored",
+            "Warning".warn().bold()
+        );
+    }
+    if args.by_owner && !owner::supported() {
+        eprintln!(
+            "{}: --by-owner is only supported on Unix and will be ignored",
+            "Warning".warn().bold()
+        );
+    }
+    if args.by_mount && !mounts::supported() {
+        eprintln!(
+            "{}: --by-mount is only supported on Unix and will be ignored",
+            "Warning".warn().bold()
+        );
+    }
+    let user = args.user.as_deref().map(owner::lookup_uid).transpose()?;
+
+    let extension_rules = ext::ExtensionRules::new(
+        args.merge_ext.as_deref(),
+        args.compound_ext.as_deref(),
+        args.case_sensitive_ext,
+    )
+    .map_err(anyhow::Error::msg)?;
+
+    if !args.merge.is_empty() {
+        return merge_snapshots(&args, &size_format, &extension_rules);
+    }
+
+    let weights = args
+        .weights
+        .as_deref()
+        .map(|file| weights::Weights::load(file, &extension_rules))
+        .transpose()?;
+
+    let excludes = exclude::Excludes::load(&args.exclude, args.exclude_from.as_deref())?;
+
+    if !args.git_diff.is_empty() {
+        return git_diff(&args, &expanded_patterns, &excludes, &size_format);
+    }
+
+    let interrupt = interrupt::Interrupt::install()?;
+    if let Some(limit) = args.timeout {
+        interrupt.set_deadline(limit);
+    }
+    let output = output::Output::new(args.flush_each);
+    let unreadable_dirs = AtomicUsize::new(0);
+    let scan_stats = stats::ScanStats::default();
+
+    // With --parallel-glob-once, wildcard patterns are expanded up front by
+    // one shared walk; literal paths still go through glob() below.
+    let mut shared_paths = Vec::new();
+    if args.parallel_glob_once {
+        let wildcards: Vec<&str> = expanded_patterns
+            .iter()
+            .map(|(_, pattern)| pattern.as_str())
+            .filter(|pattern| !patterns::is_literal(pattern))
+            .collect();
+        let shared_walk = walk::SharedWalk::new(
+            &wildcards,
+            args.max_depth,
+            !args.skip_symlinked_dirs,
+            !args.hidden,
+        )?;
         if args.debug {
-            println!("{}: {}", "Processing pattern".yellow(), pattern.cyan());
+            println!(
+                "{}: {} patterns served by {} directory walks",
+                "Shared walk".warn(),
+                wildcards.len().to_string().value(),
+                shared_walk.walk_count().to_string().value()
+            );
+        }
+        let (matched, errors) = shared_walk.run(|| interrupt.is_set());
+        for e in errors {
+            unreadable_dirs.fetch_add(1, Ordering::Relaxed);
+            if !args.ignore_unreadable {
+                eprintln!("{}: Error processing path: {}", "Warning".warn().bold(), e);
+            }
         }
+        shared_paths = matched;
+    }
+    let mut shared_paths = shared_paths.into_iter();
+    let walked: Vec<Option<Vec<PathBuf>>> = expanded_patterns
+        .iter()
+        .map(|(_, pattern)| {
+            (args.parallel_glob_once && !patterns::is_literal(pattern))
+                .then(|| shared_paths.next().unwrap_or_default())
+        })
+        .collect();
+
+    let all_candidate_paths =
+        expanded_patterns
+            .par_iter()
+            .zip(walked)
+            .map(|((index, pattern), walked)| -> Result<_> {
+                if args.debug {
+                    output
+                        .line(format!(
+                            "{}: {}",
+                            "Processing pattern".warn(),
+                            pattern.value()
+                        ))
+                        .ok();
+                }
 
-        let paths = glob(pattern).with_context(|| format!("Invalid glob pattern: {}", pattern))?;
+                // glob() always follows symlinked directories and reads
+                // hidden directories, so skipping them takes a walk even
+                // without a depth limit. Hidden trees only matter below `**`.
+                let skip_hidden = !args.hidden && !patterns::names_hidden(pattern);
+                let walk_wildcard = !patterns::is_literal(pattern)
+                    && (args.skip_symlinked_dirs || (skip_hidden && pattern.contains("**")));
+                let paths: Box<dyn Iterator<Item = Result<PathBuf>>> =
+                    match (walked, args.max_depth) {
+                        (Some(walked), _) => Box::new(walked.into_iter().map(Ok)),
+                        (None, max_depth) if max_depth.is_some() || walk_wildcard => Box::new(
+                            walk::walk(pattern, max_depth, !args.skip_symlinked_dirs, skip_hidden)?
+                                .map(|path| path.map_err(anyhow::Error::from)),
+                        ),
+                        (None, _) => Box::new(
+                            glob(pattern)
+                                .with_context(|| format!("Invalid glob pattern: {}", pattern))?
+                                .map(|path| path.map_err(anyhow::Error::from)),
+                        ),
+                    };
 
-        let mut pattern_paths = Vec::new();
-        for path in paths {
-            match path {
-                Ok(path) => {
-                    if args.debug {
-                        println!("  {} {}", "Found path:".blue(), path.display());
+                let hidden = patterns::HiddenFilter::new(pattern);
+                let mut pattern_paths = Vec::new();
+                for path in paths {
+                    if interrupt.is_set() {
+                        break;
+                    }
+                    match path {
+                        Ok(path) if !args.hidden && hidden.is_hidden(&path) => {}
+                        Ok(path) => {
+                            if args.debug {
+                                output
+                                    .line(format!("  {} {}", "Found path:".label(), path.display()))
+                                    .ok();
+                            }
+                            pattern_paths.push((*index, path));
+                        }
+                        Err(e) => {
+                            unreadable_dirs.fetch_add(1, Ordering::Relaxed);
+                            if !args.ignore_unreadable {
+                                eprintln!(
+                                    "{}: Error processing path: {}",
+                                    "Warning".warn().bold(),
+                                    e
+                                );
+                            }
+                        }
                     }
-                    pattern_paths.push(path);
                 }
-                Err(e) => {
-                    eprintln!(
-                        "{}: Error processing path: {}",
-                        "Warning".yellow().bold(),
-                        e
-                    );
+
+                if args.debug {
+                    output
+                        .line(format!(
+                            "  {} {} paths from pattern: {}",
+                            "Found".good(),
+                            pattern_paths.len().to_string().value(),
+                            pattern.value()
+                        ))
+                        .ok();
                 }
+
+                Ok(pattern_paths)
+            });
+
+    // Collecting an indexed parallel iterator keeps its order, so candidates
+    // follow the order of the patterns, then the order each pattern yields.
+    let per_pattern: Result<Vec<Vec<(usize, PathBuf)>>> = all_candidate_paths.collect();
+    output.flush()?;
+    let all_candidate_paths: Vec<(usize, PathBuf)> = per_pattern?.into_iter().flatten().collect();
+    let expand_errors = unreadable_dirs.load(Ordering::Relaxed);
+    if args.strict_glob && expand_errors > 0 {
+        anyhow::bail!(
+            "{} paths could not be read while expanding the patterns, so the results would be \
+             incomplete (--strict-glob)",
+            expand_errors
+        );
+    }
+    scan_stats
+        .seen
+        .fetch_add(all_candidate_paths.len(), Ordering::Relaxed);
+
+    if args.list {
+        warn_if_stopped(&interrupt);
+        let separator = if args.null { b"\0" } else { b"\n" };
+        let mut stdout = io::stdout().lock();
+        for (_, path) in &all_candidate_paths {
+            if args.files_only && !longpath::fs_path(path).is_file() {
+                continue;
             }
+            stdout.write_all(path.as_os_str().as_encoded_bytes())?;
+            stdout.write_all(separator)?;
         }
+        stdout.flush()?;
+        exit_with_status(&interrupt, false);
+        return Ok(());
+    }
 
-        if args.debug {
-            println!(
-                "  {} {} paths from pattern: {}",
-                "Found".green(),
-                pattern_paths.len().to_string().cyan(),
-                pattern.cyan()
-            );
+    let missing_paths: Vec<&str> = expanded_patterns
+        .par_iter()
+        .map(|(_, pattern)| pattern.as_str())
+        .filter(|pattern| {
+            patterns::is_literal(pattern)
+                && fs::symlink_metadata(longpath::fs_path(Path::new(pattern)))
+                    .is_err_and(|e| e.kind() == io::ErrorKind::NotFound)
+        })
+        .collect();
+    scan_stats
+        .missing
+        .fetch_add(missing_paths.len(), Ordering::Relaxed);
+    if !args.allow_missing {
+        for path in &missing_paths {
+            eprintln!("{}: No such file: {}", "Error".bad().bold(), path);
         }
-
-        Ok(pattern_paths)
-    });
-
-    let all_candidate_paths: Vec<PathBuf> =
-        all_candidate_paths.try_reduce(Vec::new, |mut acc, item| {
-            acc.extend(item);
-            Ok(acc)
-        })?;
+    }
 
     if args.debug {
         println!(
             "{} {} candidate paths, filtering files in parallel...",
-            "Total".green().bold(),
-            all_candidate_paths.len().to_string().cyan()
+            "Total".good().bold(),
+            all_candidate_paths.len().to_string().value()
         );
     }
 
-    let all_files: Vec<PathBuf> = all_candidate_paths
+    let throttle = throttle::Throttle::new(args.io_concurrency.map(|limit| limit.get()));
+
+    let matched_files: Vec<(usize, PathBuf)> = all_candidate_paths
         .par_iter()
-        .filter_map(|path| {
-            if path.is_file() {
+        .filter_map(|(index, path)| {
+            if interrupt.is_set() {
+                return None;
+            }
+            if args.scan_stats
+                && fs::symlink_metadata(longpath::fs_path(path))
+                    .is_ok_and(|link| link.file_type().is_symlink())
+            {
+                scan_stats.symlinks.fetch_add(1, Ordering::Relaxed);
+            }
+            if throttle.run(|| {
+                longpath::fs_path(path).is_file()
+                    || (args.skip_symlinked_dirs && walk::is_dir_symlink(path))
+            }) {
                 if args.debug {
-                    println!("    {} {} (added)", "✓".green(), path.display());
+                    output
+                        .line(format!("    {} {} (added)", "✓".good(), path.display()))
+                        .ok();
                 }
-                Some(path.clone())
+                Some((*index, path.clone()))
             } else {
                 if args.debug {
-                    println!("    {} {} (skipped)", "✗".red(), path.display());
+                    output
+                        .line(format!("    {} {} (skipped)", "✗".bad(), path.display()))
+                        .ok();
+                }
+                scan_stats.non_files.fetch_add(1, Ordering::Relaxed);
+                if args.scan_stats && longpath::fs_path(path).is_dir() {
+                    scan_stats.directories.fetch_add(1, Ordering::Relaxed);
                 }
                 None
             }
         })
         .collect();
+    output.flush()?;
+
+    let mut matched_files = matched_files;
+    if args.min_path_depth.is_some() || args.max_path_depth.is_some() {
+        let cwd = env::current_dir().context("Failed to get current directory")?;
+        let depths = args.min_path_depth.unwrap_or(0)..=args.max_path_depth.unwrap_or(usize::MAX);
+        let before = matched_files.len();
+        matched_files.retain(|(_, path)| depths.contains(&walk::path_depth(path, &cwd)));
+        scan_stats
+            .filtered
+            .fetch_add(before - matched_files.len(), Ordering::Relaxed);
+    }
+    if !excludes.is_empty() {
+        let before = matched_files.len();
+        if args.debug {
+            let mut dropped = vec![0usize; excludes.patterns().len()];
+            matched_files.retain(|(_, path)| {
+                let matching = excludes.matching(path);
+                for &index in &matching {
+                    dropped[index] += 1;
+                }
+                matching.is_empty()
+            });
+            println!(
+                "{} {} exclude patterns",
+                "Loaded".good(),
+                excludes.patterns().len().to_string().value()
+            );
+            for (pattern, dropped) in excludes.patterns().iter().zip(dropped) {
+                println!(
+                    "  {}: dropped {} paths",
+                    pattern.value(),
+                    dropped.to_string().value()
+                );
+            }
+        } else {
+            matched_files.retain(|(_, path)| !excludes.is_excluded(path));
+        }
+        scan_stats
+            .excluded
+            .fetch_add(before - matched_files.len(), Ordering::Relaxed);
+    }
 
-    if all_files.is_empty() {
-        println!("{}", "No files found matching the patterns".yellow());
+    if args.git_tracked {
+        let tracked = git::TrackedFiles::load()?;
+        let before = matched_files.len();
+        matched_files.retain(|(_, path)| tracked.contains(path));
+        if args.debug {
+            println!(
+                "{} {} untracked files ({} paths tracked by git)",
+                "Dropped".good(),
+                (before - matched_files.len()).to_string().value(),
+                tracked.len().to_string().value()
+            );
+        }
+    }
 
+    let mut pattern_file_counts = vec![0usize; args.patterns.len()];
+    for (index, _) in &matched_files {
+        pattern_file_counts[*index] += 1;
+    }
+    // Which patterns matched each path, for --pattern-report.
+    let mut pattern_sources: HashMap<PathBuf, BTreeSet<usize>> = HashMap::new();
+    if args.pattern_report {
+        for (index, path) in &matched_files {
+            pattern_sources
+                .entry(path.clone())
+                .or_default()
+                .insert(*index);
+        }
+    }
+    // --verify starts again from every match, before any deduplication.
+    let verify_paths: Vec<PathBuf> = if args.verify {
+        matched_files.iter().map(|(_, path)| path.clone()).collect()
+    } else {
+        Vec::new()
+    };
+    let mut seen = HashSet::new();
+    let all_files: Vec<PathBuf> = matched_files
+        .into_iter()
+        .map(|(_, path)| path)
+        .filter(|path| !args.dedup_overlaps || seen.insert(path.clone()))
+        .collect();
+
+    let mut collapsed_paths = Vec::new();
+    let all_files = if args.canonical_dedup {
+        let (kept, collapsed) = dedup::canonical_dedup(all_files);
         if args.debug {
-            println!("\n{}", "Debug suggestions:".cyan().bold());
+            println!(
+                "{} {} paths leading to already matched files",
+                "Collapsed".good(),
+                collapsed.len().to_string().value()
+            );
+        }
+        for (dropped, kept) in &collapsed {
+            if let Some(sources) = pattern_sources.remove(dropped) {
+                pattern_sources
+                    .entry(kept.clone())
+                    .or_default()
+                    .extend(sources);
+            }
+        }
+        collapsed_paths.extend(collapsed);
+        kept
+    } else {
+        all_files
+    };
+
+    let empty_patterns: Vec<&str> = args
+        .patterns
+        .iter()
+        .zip(&pattern_file_counts)
+        .filter(|(_, count)| **count == 0)
+        .map(|(pattern, _)| pattern.as_str())
+        .collect();
+
+    if args.fail_glob && !empty_patterns.is_empty() {
+        anyhow::bail!("No files matched: {}", empty_patterns.join(", "));
+    }
+
+    let min_count = args.min_count.unwrap_or(0).max(args.fail_on_empty as usize);
+    if all_files.len() < min_count {
+        let mut message = format!(
+            "Expected at least {} files, but only {} matched",
+            min_count,
+            all_files.len()
+        );
+        if !empty_patterns.is_empty() {
+            message.push_str(&format!(
+                "; patterns with no matches: {}",
+                empty_patterns.join(", ")
+            ));
+        }
+        return Err(anyhow::anyhow!(message));
+    }
+
+    // Machine-readable output stays valid when nothing matched: the notice
+    // goes to stderr and the run goes on to write an empty report, `0`, etc.
+    // A stopped scan likewise goes on to report its (empty) partial results.
+    let machine_output = !args.human_output() || args.report_format().is_some();
+    if all_files.is_empty() && machine_output {
+        eprintln!("{}", "No files found matching the patterns".warn());
+    }
+
+    if all_files.is_empty() && !machine_output && !interrupt.is_set() {
+        println!("{}", "No files found matching the patterns".warn());
+
+        if args.debug {
+            println!("\n{}", "Debug suggestions:".value().bold());
             println!(
                 "• Current directory: {}",
                 env::current_dir()
                     .map(|p| p.display().to_string())
                     .unwrap_or_else(|_| "Unknown".to_string())
-                    .yellow()
+                    .warn()
             );
             println!("• Try running from the directory where your files are located");
             println!("• Check if the file extensions are correct");
             println!(
-                "• In Nushell, use separate patterns: {} instead of {}",
-                "**/*.png **/*.jpg **/*.dds".green(),
-                "**/*.{png,jpg,dds}".red().strikethrough()
+                "• Quote patterns so your shell passes them through unchanged: {}",
+                "weight '**/*.{png,jpg,dds}'".good()
             );
             println!(
                 "• Try a simpler pattern like {} or {}",
-                "*.png".green(),
-                "./**/*.png".green()
+                "*.png".good(),
+                "./**/*.png".good()
             );
-            println!("• Check directory permissions with: {}", "ls -la".cyan());
+            println!("• Check directory permissions with: {}", "ls -la".value());
         } else {
             println!(
                 "{} Use {} flag for debug information",
-                "Tip:".blue().bold(),
-                "--debug".cyan()
+                "Tip:".label().bold(),
+                "--debug".value()
             );
         }
 
         return Ok(());
     }
 
-    println!(
-        "{} {} files, calculating sizes...",
-        "Found".green().bold(),
-        all_files.len().to_string().cyan().bold()
-    );
+    if args.dry_run {
+        warn_if_stopped(&interrupt);
+        for path in &all_files {
+            println!("{}", path_display.show(path).label());
+        }
+        println!(
+            "\n{} {} files would be measured",
+            "Dry run:".value().bold(),
+            all_files.len().to_string().value().bold()
+        );
+        exit_with_status(&interrupt, false);
+        return Ok(());
+    }
 
-    let results: Vec<Result<(PathBuf, u64)>> = all_files
-        .par_iter()
-        .map(|path| {
-            let metadata = fs::metadata(path)
-                .with_context(|| format!("Failed to read metadata for: {}", path.display()))?;
-            Ok((path.clone(), metadata.len()))
-        })
-        .collect();
+    let single_file = all_files.len() == 1
+        && args.human_output()
+        && !args.no_flatten_single
+        && !args.parents
+        && args.top_dirs.is_none()
+        && args.group_by().is_none()
+        && args.bucket_by.is_none()
+        && args.vs_free.is_none()
+        && args.round_up_to.is_none()
+        && args.count_names.is_none()
+        && !args.hardlink_stats
+        && !args.extensionless
+        && !args.near_dup
+        && !args.estimate_compressed
+        && args.weights.is_none()
+        && !args.ext_case_report
+        && !args.dedup_report
+        && !args.pattern_report;
+
+    if args.human_output() && !single_file && !args.no_summary {
+        println!(
+            "{} {} files, calculating sizes...",
+            "Found".good().bold(),
+            all_files.len().to_string().value().bold()
+        );
+    }
+
+    if args.checksum.is_some() {
+        eprintln!(
+            "{}: --checksum reads the full contents of every file and may be slow",
+            "Note".warn().bold()
+        );
+    }
+
+    let entry_line = |entry: &FileEntry| {
+        let size_str = size_format.format_field(entry.size);
+        match &entry.checksum {
+            Some(hash) => format!(
+                "{}: {} {}",
+                path_display.show(&entry.path).label(),
+                size_str.good(),
+                hash.muted()
+            ),
+            None => format!(
+                "{}: {}",
+                path_display.show(&entry.path).label(),
+                size_str.good()
+            ),
+        }
+    };
+
+    let profiler = args.profile.then(profile::Profiler::new);
+    let measure_started = Instant::now();
+
+    let measure_errors = AtomicUsize::new(0);
+    let too_many_errors =
+        |count: usize| args.abort_after_errors.is_some_and(|limit| count >= limit);
+
+    let progress = progress::Progress::default();
+    // --pattern-report still needs the paths once they have been measured;
+    // otherwise each one moves into its entry instead of being copied.
+    let file_count = all_files.len();
+    let kept_files = if args.pattern_report {
+        all_files.clone()
+    } else {
+        Vec::new()
+    };
+    let measure_all = || -> Vec<Result<FileEntry>> {
+        all_files
+            .into_par_iter()
+            .map(|path| {
+                if interrupt.is_set() || too_many_errors(measure_errors.load(Ordering::Relaxed)) {
+                    return None;
+                }
+                let started = Instant::now();
+                let measured = throttle.run(|| measure(path, &args, user, &extension_rules));
+                scan_stats.stated.fetch_add(1, Ordering::Relaxed);
+                let entry = match measured {
+                    Ok(Measured::File(entry)) => Some(Ok(entry)),
+                    Ok(Measured::Missing) => {
+                        scan_stats.missing.fetch_add(1, Ordering::Relaxed);
+                        None
+                    }
+                    Ok(Measured::Filtered) => {
+                        scan_stats.filtered.fetch_add(1, Ordering::Relaxed);
+                        None
+                    }
+                    Err(e) => Some(Err(e)),
+                };
+                if let Some(Err(_)) = &entry {
+                    measure_errors.fetch_add(1, Ordering::Relaxed);
+                }
+                progress.record(match &entry {
+                    Some(Ok(entry)) => entry.size,
+                    _ => 0,
+                });
+                if let Some(profiler) = &profiler {
+                    let bytes = match &entry {
+                        Some(Ok(entry)) => entry.size,
+                        _ => 0,
+                    };
+                    profiler.record(bytes, started.elapsed());
+                }
+                if let (true, Some(Ok(entry))) = (args.json_stream, &entry) {
+                    output
+                        .line(report::file_json(entry, args.encoding.unwrap_or_default()))
+                        .ok();
+                }
+                Some(entry)
+            })
+            .while_some()
+            .flatten()
+            .collect()
+    };
+    let results = match args.progress_interval {
+        Some(interval) if !io::stdout().is_terminal() => {
+            progress.log_while(interval, file_count, &size_format, measure_all)
+        }
+        _ => measure_all(),
+    };
+
+    let measure_errors = measure_errors.into_inner();
+    if too_many_errors(measure_errors) {
+        output.flush()?;
+        eprintln!(
+            "{}: aborting after {} errors: too many errors, the filesystem may be unavailable",
+            "Error".bad().bold(),
+            measure_errors
+        );
+        std::process::exit(EXIT_TOO_MANY_ERRORS);
+    }
+
+    let measure_time = measure_started.elapsed();
+
+    if let Some(profiler) = &profiler {
+        profiler.report(&size_format);
+    }
+    if args.scan_stats {
+        scan_stats.report();
+    }
+
+    let interrupted = interrupt.is_set();
+    output.flush()?;
+    warn_if_stopped(&interrupt);
+
+    let mut results = results;
+    if args.aggregate_symlinks {
+        let collapsed = dedup::collapse_symlinks(&mut results);
+        if args.debug {
+            println!(
+                "{} {} symlinks pointing at already counted files",
+                "Collapsed".good(),
+                collapsed.len().to_string().value()
+            );
+        }
+        collapsed_paths.extend(collapsed);
+    }
 
     let mut total_size = 0u64;
-    let mut error_count = 0;
+    let mut rounded_size = 0u64;
+    let mut error_count = if args.allow_missing {
+        0
+    } else {
+        missing_paths.len()
+    };
+    let mut entries = Vec::new();
 
     for result in results {
         match result {
-            Ok((path, size)) => {
-                total_size += size;
-                if args.verbose {
-                    let size_str = format_size(size);
-
-                    println!(
-                        "{}: {}",
-                        path.display().to_string().blue(),
-                        size_str.green()
-                    );
+            Ok(entry) => {
+                total_size = add_size(total_size, entry.size)?;
+                if let Some(unit) = args.round_up_to {
+                    let rounded = size::round_up(entry.size, unit).ok_or_else(|| {
+                        anyhow::anyhow!("Rounded size of {} overflowed u64", entry.path.display())
+                    })?;
+                    rounded_size = add_size(rounded_size, rounded)?;
+                }
+                if args.verbose && args.human_output() && !single_file && args.sort.is_none() {
+                    output.line(entry_line(&entry))?;
                 }
+                entries.push(entry);
             }
             Err(e) => {
-                eprintln!("{}: {}", "Error".red().bold(), e);
+                output.flush()?;
+                eprintln!("{}: {}", "Error".bad().bold(), e);
                 error_count += 1;
             }
         }
     }
 
-    println!("\n{}", "--- Summary ---".cyan().bold());
+    if args.verify && !interrupt.is_set() {
+        let options = verify::Options {
+            dedup_overlaps: args.dedup_overlaps,
+            canonical_dedup: args.canonical_dedup,
+            aggregate_symlinks: args.aggregate_symlinks,
+            skip_symlinked_dirs: args.skip_symlinked_dirs,
+        };
+        // Files whose contents cannot be read fail to measure when they are
+        // hashed or compressed.
+        let reads_contents = args.checksum.is_some() || args.estimate_compressed;
+        let (reference_size, reference_count) =
+            verify::reference_total(&verify_paths, &options, |path, metadata| {
+                Ok(
+                    passes_filters(path, metadata, &args, user, &extension_rules)?
+                        && (!reads_contents
+                            || metadata.is_dir()
+                            || fs::File::open(longpath::fs_path(path)).is_ok()),
+                )
+            });
+        if (reference_size, reference_count) != (total_size, entries.len()) {
+            anyhow::bail!(
+                "Verification failed: the parallel pipeline counted {} files totalling {} bytes, \
+                 but the sequential reference counted {} files totalling {} bytes",
+                entries.len(),
+                total_size,
+                reference_count,
+                reference_size
+            );
+        }
+        output.flush()?;
+        eprintln!(
+            "{} verified: the sequential reference agrees on {} files totalling {} bytes",
+            "✓".good(),
+            reference_count,
+            reference_size
+        );
+    }
+
+    // A partial total says nothing about the expectation.
+    let unexpected_total =
+        !interrupt.is_set() && check_expected_total(&args, total_size, &size_format);
+
+    if args.throughput {
+        let seconds = measure_time.as_secs_f64();
+        let rate = if seconds > 0.0 {
+            total_size as f64 / seconds
+        } else {
+            0.0
+        };
+        output.flush()?;
+        eprintln!(
+            "{}: processed {} in {:.1?} ({}/s)",
+            "Throughput".good(),
+            size_format.format(total_size),
+            measure_time,
+            size_format.format(rate as u64)
+        );
+    }
+
+    if let Some(order) = args.sort {
+        sort::sort_entries(&mut entries, order, collator.as_ref());
+        if args.verbose && args.human_output() && !single_file {
+            for entry in &entries {
+                output.line(entry_line(entry))?;
+            }
+        }
+    }
+
+    output.flush()?;
+
+    if let Some(database) = &args.sqlite {
+        sqlite::write(
+            database,
+            &entries,
+            &extension_rules,
+            args.encoding.unwrap_or_default(),
+        )?;
+    }
+
+    if let Some(svg) = &args.treemap_svg {
+        let base = env::current_dir().context("Failed to get current directory")?;
+        treemap::write(
+            svg,
+            &rollup::tree(&entries, &base),
+            &extension_rules,
+            &size_format,
+        )?;
+    }
+
+    if let Some(format) = args.report_format() {
+        let default_encoding = match format {
+            report::Format::Json => encoding::PathEncoding::Lossy,
+            report::Format::Csv => encoding::PathEncoding::Escape,
+        };
+        let report = report::Report {
+            format,
+            entries: &entries,
+            totals: report::Totals {
+                total_bytes: total_size,
+                file_count: entries.len(),
+                error_count,
+            },
+            run: report::RunInfo::new(&args.patterns, args.deterministic),
+            encoding: args.encoding.unwrap_or(default_encoding),
+            groups: args.groups(&entries, &extension_rules),
+            compact: args.json_compact,
+            units_in_header: args.units_in_header,
+        };
+        if let Some(dir) = &args.shard_output {
+            write_shards(&args, dir, &report, &extension_rules)?;
+        } else {
+            let (mut writer, has_content) =
+                report::open_destination(args.output.as_deref(), args.append)?;
+            report.write(&mut writer, args.append, has_content)?;
+
+            if args.output.is_none() {
+                exit_with_status(&interrupt, unexpected_total);
+                return Ok(());
+            }
+        }
+    }
+
+    if args.interactive {
+        return tui::run(&entries, &size_format, &extension_rules);
+    }
+
+    if let ([entry], true, 0) = (entries.as_slice(), single_file, error_count) {
+        println!("{}", entry_line(entry));
+        exit_with_status(&interrupt, unexpected_total);
+        return Ok(());
+    }
+
+    if args.raw {
+        println!("{}", total_size);
+        exit_with_status(&interrupt, unexpected_total);
+        return Ok(());
+    }
+
+    if args.largest_dir {
+        let base = env::current_dir().context("Failed to get current directory")?;
+        if let Some((dir, size)) = rollup::top_dirs(&entries, 1, &base).pop() {
+            println!("{}\t{}", dir.display(), size);
+        }
+        exit_with_status(&interrupt, unexpected_total);
+        return Ok(());
+    }
+
+    if let Some(snapshot_path) = &args.since {
+        let snapshot = if args.update_snapshot && !snapshot_path.exists() {
+            snapshot::Snapshot::from_entries(&[], 0)
+        } else {
+            snapshot::Snapshot::load(snapshot_path)?
+        };
+        let growth = i128::from(total_size) - i128::from(snapshot.total_bytes);
+        let direction = if args.reverse_diff { -1 } else { 1 };
+
+        if args.verbose || args.diff_threshold.is_some() {
+            let snapshot_label = format!("snapshot {}", snapshot_path.display());
+            let (baseline, compared) = if args.reverse_diff {
+                ("current scan".to_string(), snapshot_label)
+            } else {
+                (snapshot_label, "current scan".to_string())
+            };
+            println!(
+                "{}: {} (changes show {} minus baseline)",
+                "Baseline".value().bold(),
+                baseline,
+                compared
+            );
+            print_changes(
+                &snapshot.changes(&entries),
+                direction,
+                args.diff_threshold.unwrap_or(0),
+                &size_format,
+            );
+        }
+
+        println!("{}", size_format.format_delta(growth * direction));
+
+        if args.update_snapshot && interrupted {
+            eprintln!(
+                "{}: scan {}, not updating snapshot {}",
+                "Warning".warn().bold(),
+                stop_reason(&interrupt),
+                snapshot_path.display()
+            );
+        } else if args.update_snapshot {
+            snapshot::Snapshot::from_entries(&entries, total_size).save(snapshot_path)?;
+        }
+
+        if let Some(limit) = args.alert_over
+            && growth > i128::from(limit)
+        {
+            anyhow::bail!(
+                "Growth of {} exceeds the alert threshold of {}",
+                size_format.format(growth as u64),
+                size_format.format(limit)
+            );
+        }
+        exit_with_status(&interrupt, unexpected_total);
+        return Ok(());
+    }
+
+    if args.json_stream {
+        println!(
+            "{}",
+            serde_json::json!({
+                "total_bytes": total_size,
+                "file_count": entries.len(),
+                "error_count": error_count,
+            })
+        );
+        exit_with_status(&interrupt, unexpected_total);
+        return Ok(());
+    }
+
+    if args.parents {
+        println!("\n{}", "--- Directories ---".value().bold());
+        for (dir, size) in rollup::cumulative_dir_sizes(&entries) {
+            println!(
+                "{}: {}",
+                dir.display().to_string().label(),
+                size_format.format(size).good()
+            );
+        }
+    }
+
+    if let Some(n) = args.top_dirs {
+        println!(
+            "\n{}",
+            format!("--- Top {} directories ---", n).value().bold()
+        );
+        let base = env::current_dir().context("Failed to get current directory")?;
+        for (dir, size) in rollup::top_dirs(&entries, n, &base) {
+            println!(
+                "{}: {} ({})",
+                dir.display().to_string().label(),
+                size_format.format(size).good(),
+                percent_of(size, total_size)
+            );
+        }
+    }
+
+    if let Some((group_by, groups)) = args.groups(&entries, &extension_rules) {
+        print_groups(group_by, &groups, &size_format, args.group_warn_over);
+    }
+
+    if args.ext_case_report {
+        println!("\n{}", "--- Extension casing ---".value().bold());
+        let mixed = ext::mixed_case(&entries, args.compound_ext.as_deref());
+        if mixed.is_empty() {
+            println!(
+                "{}",
+                "Every extension is written with a single casing".good()
+            );
+        }
+        for group in &mixed {
+            println!(
+                "{}: {} casings",
+                group.ext.warn().bold(),
+                group.casings.len().to_string().value()
+            );
+            for (casing, paths) in &group.casings {
+                let paths: Vec<String> = paths.iter().map(|path| path_display.show(path)).collect();
+                println!(
+                    "  {} ({} files): {}",
+                    casing.label(),
+                    paths.len().to_string().value(),
+                    paths.join(", ")
+                );
+            }
+        }
+    }
+
+    if args.near_dup {
+        println!(
+            "\n{}",
+            "--- Probable duplicates (sampled, may include false positives) ---"
+                .value()
+                .bold()
+        );
+        let groups = neardup::find(&entries);
+        for group in &groups {
+            println!(
+                "{} x {} ({} reclaimable)",
+                group.paths.len().to_string().value(),
+                size_format.format(group.size).good(),
+                size_format.format(group.reclaimable()).good()
+            );
+            for path in &group.paths {
+                println!("  {}", path_display.show(path).label());
+            }
+        }
+        println!(
+            "{}: {} groups, up to {} reclaimable",
+            "Probable duplicates".good(),
+            groups.len().to_string().value(),
+            size_format
+                .format(
+                    groups
+                        .iter()
+                        .fold(0u64, |sum, group| sum.saturating_add(group.reclaimable()))
+                )
+                .value()
+        );
+    }
+
+    if args.extensionless {
+        println!("\n{}", "--- Without extension ---".value().bold());
+        let mut bare: Vec<&FileEntry> = entries
+            .iter()
+            .filter(|entry| extension_rules.extension(&entry.path).is_none())
+            .collect();
+        bare.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        for entry in &bare {
+            println!(
+                "{}: {}",
+                path_display.show(&entry.path).label(),
+                size_format.format(entry.size).good()
+            );
+        }
+        println!(
+            "{}: {} files, {}",
+            "Without extension".good(),
+            bare.len().to_string().value(),
+            size_format
+                .format(bare.iter().map(|entry| entry.size).sum())
+                .value()
+        );
+    }
+
+    if args.pattern_report {
+        println!("\n{}", "--- Patterns ---".value().bold());
+        let rows = patterns::attribute(args.patterns.len(), &kept_files, &pattern_sources);
+        let width = args
+            .patterns
+            .iter()
+            .map(|pattern| pattern.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("PATTERN".len());
+        println!(
+            "{:<width$}  {:>8}  {:>8}  {:>8}  {:>8}",
+            "PATTERN", "MATCHES", "UNIQUE", "OVERLAP", "FIRST"
+        );
+        for (pattern, row) in args.patterns.iter().zip(&rows) {
+            println!(
+                "{:<width$}  {:>8}  {:>8}  {:>8}  {:>8}",
+                pattern, row.matches, row.unique, row.overlap, row.won
+            );
+        }
+    }
+
+    if args.dedup_report {
+        println!("\n{}", "--- Deduplication ---".value().bold());
+        let groups = dedup::collapsed_groups(&collapsed_paths, &entries);
+        let saved = groups
+            .iter()
+            .fold(0u64, |saved, group| saved.saturating_add(group.saved()));
+        for group in &groups {
+            let dropped: Vec<String> = group
+                .dropped
+                .iter()
+                .map(|path| path_display.show(path))
+                .collect();
+            println!(
+                "{} ({}, {} saved): {}",
+                path_display.show(&group.kept).label(),
+                size_format.format(group.size).good(),
+                size_format.format(group.saved()).good(),
+                dropped.join(", ")
+            );
+        }
+        println!(
+            "{}: {} paths collapsed, {} saved ({} counted instead of {})",
+            "Deduplicated".good(),
+            collapsed_paths.len().to_string().value(),
+            size_format.format(saved).value(),
+            size_format.format(total_size).value(),
+            size_format.format(total_size.saturating_add(saved)).value()
+        );
+    }
+
+    if let Some(boundaries) = &args.bucket_by {
+        let tiers = buckets::bucket(&entries, boundaries);
+        let label_width = tiers.iter().map(|tier| tier.label.chars().count()).max();
+
+        println!("\n{}", "--- By size tier ---".value().bold());
+        for tier in &tiers {
+            let percent = if total_size == 0 {
+                0.0
+            } else {
+                tier.bytes as f64 / total_size as f64 * 100.0
+            };
+            println!(
+                "{:<width$}  {:>10}  {:>5.1}%  ({} files)",
+                tier.label.label(),
+                size_format.format(tier.bytes).good(),
+                percent,
+                tier.count.to_string().value(),
+                width = label_width.unwrap_or(0)
+            );
+        }
+    }
+
+    let compressed_size: u64 = entries.iter().filter_map(|entry| entry.compressed).sum();
+    if args.estimate_compressed {
+        println!(
+            "\n{}",
+            "--- Estimated compression by extension (sampled) ---"
+                .value()
+                .bold()
+        );
+        for estimate in compress::by_extension(&entries, &extension_rules) {
+            println!(
+                "{}: {} -> ~{} ({}) ({} files)",
+                estimate.key.label(),
+                size_format.format(estimate.bytes).good(),
+                size_format.format(estimate.compressed).good(),
+                percent_of(estimate.compressed, estimate.bytes),
+                estimate.count.to_string().value()
+            );
+        }
+    }
+
+    if !args.no_summary {
+        println!("\n{}", "--- Summary ---".value().bold());
+        println!(
+            "{}: {}",
+            "Files processed".good(),
+            entries.len().to_string().value().bold()
+        );
+
+        if error_count > 0 {
+            println!(
+                "{}: {}",
+                "Errors".bad().bold(),
+                error_count.to_string().bad()
+            );
+        }
+
+        let missing = scan_stats.missing.load(Ordering::Relaxed);
+        if args.allow_missing && missing > 0 {
+            println!(
+                "{}: {}",
+                "Missing paths skipped".warn(),
+                missing.to_string().warn()
+            );
+        }
+
+        let unreadable_dirs = unreadable_dirs.into_inner();
+        if unreadable_dirs > 0 {
+            println!(
+                "{}: {}",
+                "Unreadable directories skipped".warn(),
+                unreadable_dirs.to_string().warn()
+            );
+        }
+
+        let total_size_str = size_format.format_total(total_size);
+
+        if interrupted {
+            println!(
+                "{}: {}",
+                format!("Total size (partial, {})", stop_reason(&interrupt))
+                    .as_str()
+                    .warn()
+                    .bold(),
+                total_size_str.total().bold()
+            );
+        } else {
+            println!(
+                "{}: {}",
+                "Total size".good().bold(),
+                total_size_str.total().bold()
+            );
+        }
+    }
+
+    if let Some(weights) = &weights {
+        println!(
+            "{}: {}",
+            "Weighted total".good().bold(),
+            size_format
+                .format(weights.total(&entries, &extension_rules))
+                .total()
+                .bold()
+        );
+    }
+
+    if args.estimate_compressed {
+        println!(
+            "{}: ~{} ({}, saves ~{})",
+            "Estimated compressed size".good(),
+            size_format.format(compressed_size).total().bold(),
+            percent_of(compressed_size, total_size),
+            size_format
+                .format(total_size.saturating_sub(compressed_size))
+                .value()
+        );
+    }
+
+    if let Some(unit) = args.round_up_to {
+        println!(
+            "{}: {} ({} overhead)",
+            format!("Rounded up to {} units", size_format.format(unit)).good(),
+            size_format.format(rounded_size).total().bold(),
+            size_format.format(rounded_size - total_size).value()
+        );
+    }
+
+    if args.hardlink_stats && cfg!(unix) {
+        let stats = dedup::hardlink_stats(&entries);
+        println!(
+            "{}: {} files with more than one link, {} distinct files",
+            "Hard links".good(),
+            stats.files.to_string().value(),
+            stats.inodes.to_string().value()
+        );
+    }
+
+    if let Some(cost) = args.count_names {
+        let overhead = names::overhead(&entries, cost);
+        println!(
+            "{}: {} (not included in the total size)",
+            "Names overhead".good(),
+            size_format.format(overhead).value()
+        );
+    }
+
+    if let Some(destination) = &args.vs_free {
+        let free = fs4::available_space(destination).with_context(|| {
+            format!("Failed to query free space for: {}", destination.display())
+        })?;
+        let remaining = free.checked_sub(total_size);
+
+        println!(
+            "{}: {}",
+            format!("Free space on {}", destination.display()).good(),
+            size_format.format(free).value()
+        );
+        match remaining {
+            Some(remaining) => println!(
+                "{}: {}",
+                "Free after copy".good(),
+                size_format.format(remaining).value()
+            ),
+            None => println!(
+                "{}: short by {}",
+                "Does not fit".bad().bold(),
+                size_format.format(total_size - free).bad()
+            ),
+        }
+
+        if let Some(min_free) = args.min_free {
+            if remaining.is_some_and(|remaining| remaining >= min_free) {
+                println!(
+                    "{} at least {} would remain free",
+                    "✓".good(),
+                    size_format.format(min_free)
+                );
+            } else {
+                eprintln!(
+                    "{} copying {} would leave less than {} free on {}",
+                    "✗".bad(),
+                    size_format.format(total_size),
+                    size_format.format(min_free),
+                    destination.display()
+                );
+                std::process::exit(EXIT_NOT_ENOUGH_SPACE);
+            }
+        }
+    }
+
+    exit_with_status(&interrupt, unexpected_total);
+    Ok(())
+}
+
+/// Warns on stderr that the results that follow are partial when the scan
+/// was interrupted or timed out.
+fn warn_if_stopped(interrupt: &interrupt::Interrupt) {
+    if interrupt.is_set() {
+        eprintln!(
+            "{}: {}, the results below are partial",
+            "Warning".warn().bold(),
+            stop_reason(interrupt)
+        );
+    }
+}
+
+fn stop_reason(interrupt: &interrupt::Interrupt) -> &'static str {
+    if interrupt.timed_out() {
+        "timed out"
+    } else {
+        "interrupted"
+    }
+}
+
+/// Exits with the code for a stopped scan, or for a total outside
+/// `--expect-total`, once the results have been written.
+fn exit_with_status(interrupt: &interrupt::Interrupt, unexpected_total: bool) {
+    if interrupt.timed_out() {
+        std::process::exit(EXIT_TIMED_OUT);
+    }
+    if interrupt.is_set() {
+        std::process::exit(EXIT_INTERRUPTED);
+    }
+    if unexpected_total {
+        std::process::exit(EXIT_UNEXPECTED_TOTAL);
+    }
+}
+
+/// Compares the total with `--expect-total`, printing the actual and
+/// expected sizes and the delta to stderr. Returns whether the total is
+/// outside the tolerance.
+fn check_expected_total(args: &Args, total: u64, size_format: &size::SizeFormat) -> bool {
+    let Some(expected) = args.expect_total else {
+        return false;
+    };
+    let tolerance = args.tolerance.unwrap_or(size::Tolerance::Bytes(0));
+    let allowed = tolerance.of(expected);
+    let delta = i128::from(total) - i128::from(expected);
+    let outside = delta.unsigned_abs() > u128::from(allowed);
+
+    let range = match tolerance {
+        size::Tolerance::Percent(percent) => {
+            format!("± {}% ({})", percent, size_format.format(allowed))
+        }
+        size::Tolerance::Bytes(_) => format!("± {}", size_format.format(allowed)),
+    };
+    let (mark, verdict) = if outside {
+        ("✗".bad(), "outside")
+    } else {
+        ("✓".good(), "within")
+    };
+    eprintln!(
+        "{} total {} is {} the expected {} {}: {}",
+        mark,
+        size_format.format(total),
+        verdict,
+        size_format.format(expected),
+        range,
+        size_format.format_delta(delta)
+    );
+    outside
+}
+
+/// Prints a grouping table. Groups larger than `warn_over` are highlighted.
+fn print_groups(
+    group_by: groups::GroupBy,
+    groups: &[groups::Group],
+    size_format: &size::SizeFormat,
+    warn_over: Option<u64>,
+) {
     println!(
-        "{}: {}",
-        "Files processed".green(),
-        (all_files.len() - error_count).to_string().cyan().bold()
+        "\n{}",
+        format!("--- {} ---", group_by.title()).value().bold()
     );
+    for group in groups {
+        let size = size_format.format(group.bytes);
+        let size = if warn_over.is_some_and(|limit| group.bytes > limit) {
+            size.bad().bold()
+        } else {
+            size.good()
+        };
+        let exact = size_format
+            .exact_bytes(group.bytes)
+            .map(|bytes| format!("{} bytes, ", bytes))
+            .unwrap_or_default();
+        println!(
+            "{}: {} ({}{} files)",
+            group.key.label(),
+            size,
+            exact,
+            group.count.to_string().value()
+        );
+    }
+}
 
-    if error_count > 0 {
+/// Prints each change whose size moved by more than `threshold`, with its
+/// delta multiplied by `direction` and a note for added or removed files.
+fn print_changes(
+    changes: &[snapshot::FileChange],
+    direction: i128,
+    threshold: u64,
+    size_format: &size::SizeFormat,
+) {
+    let threshold = i128::from(threshold);
+    for change in changes {
+        let delta = change.delta() * direction;
+        if delta.abs() <= threshold {
+            continue;
+        }
+        let note = match (change.before, change.after) {
+            (None, _) => " (added)",
+            (_, None) => " (removed)",
+            _ => "",
+        };
         println!(
-            "{}: {}",
-            "Errors".red().bold(),
-            error_count.to_string().red()
+            "{}: {}{}",
+            change.path.label(),
+            size_format.format_delta(delta).good(),
+            note
         );
     }
+}
 
-    let total_size_str = format_size(total_size);
+/// Compares the sizes of matched files between two git revisions, read from
+/// the object database without checking either one out.
+fn git_diff(
+    args: &Args,
+    patterns: &[(usize, String)],
+    excludes: &exclude::Excludes,
+    size_format: &size::SizeFormat,
+) -> Result<()> {
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..glob::MatchOptions::new()
+    };
+    let matchers = patterns
+        .iter()
+        .map(|(_, pattern)| {
+            let matcher = glob::Pattern::new(pattern.strip_prefix("./").unwrap_or(pattern))
+                .with_context(|| format!("Invalid glob pattern: {}", pattern))?;
+            Ok((matcher, patterns::HiddenFilter::new(pattern)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let matched = |path: &String| {
+        let path = Path::new(path);
+        !excludes.is_excluded(path)
+            && matchers.iter().any(|(matcher, hidden)| {
+                matcher.matches_path_with(path, options) && (args.hidden || !hidden.is_hidden(path))
+            })
+    };
+
+    let [before, after] = [&args.git_diff[0], &args.git_diff[1]].map(|rev| {
+        git::tree_sizes(rev).map(|sizes| {
+            sizes
+                .into_iter()
+                .filter(|(path, _)| matched(path))
+                .collect::<Vec<_>>()
+        })
+    });
+    let (before, after) = (before?, after?);
+    let before_total: u64 = before.iter().map(|(_, size)| size).sum();
+    let after_total: u64 = after.iter().map(|(_, size)| size).sum();
+    let (before_count, after_count) = (before.len(), after.len());
+    let changes = snapshot::diff(before, after);
 
+    println!(
+        "{}: {} (changes show {} minus baseline)",
+        "Baseline".value().bold(),
+        args.git_diff[0],
+        args.git_diff[1]
+    );
+    print_changes(&changes, 1, 0, size_format);
+
+    println!("\n{}", "--- Summary ---".value().bold());
+    for (rev, count, total) in [
+        (&args.git_diff[0], before_count, before_total),
+        (&args.git_diff[1], after_count, after_total),
+    ] {
+        println!(
+            "{}: {} in {} files",
+            rev.as_str().label(),
+            size_format.format(total).good(),
+            count.to_string().value()
+        );
+    }
+    let count = |wanted: fn(&snapshot::FileChange) -> bool| {
+        changes.iter().filter(|change| wanted(change)).count()
+    };
+    println!(
+        "{}: {} added, {} removed, {} resized",
+        "Changed files".good(),
+        count(|change| change.before.is_none()).to_string().value(),
+        count(|change| change.after.is_none()).to_string().value(),
+        count(|change| change.before.is_some() && change.after.is_some())
+            .to_string()
+            .value()
+    );
     println!(
         "{}: {}",
-        "Total size".green().bold(),
-        total_size_str.magenta().bold()
+        "Total delta".good().bold(),
+        size_format
+            .format_delta(i128::from(after_total) - i128::from(before_total))
+            .total()
+            .bold()
     );
+    Ok(())
+}
+
+/// Reports on the combined contents of the `--merge` snapshots.
+fn merge_snapshots(
+    args: &Args,
+    size_format: &size::SizeFormat,
+    extension_rules: &ext::ExtensionRules,
+) -> Result<()> {
+    let merged = merge::merge(&args.merge, args.merge_qualify)?;
+    let mut total_size = 0u64;
+    for entry in &merged.entries {
+        total_size = add_size(total_size, entry.size)?;
+    }
+
+    if let Some(format) = args.report_format() {
+        let patterns: Vec<String> = args
+            .merge
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        let report = report::Report {
+            format,
+            entries: &merged.entries,
+            totals: report::Totals {
+                total_bytes: total_size,
+                file_count: merged.entries.len(),
+                error_count: 0,
+            },
+            run: report::RunInfo::new(&patterns, args.deterministic),
+            encoding: args.encoding.unwrap_or_default(),
+            groups: args.groups(&merged.entries, extension_rules),
+            compact: args.json_compact,
+            units_in_header: args.units_in_header,
+        };
+        let (mut writer, has_content) =
+            report::open_destination(args.output.as_deref(), args.append)?;
+        report.write(&mut writer, args.append, has_content)?;
+        if args.output.is_none() {
+            return Ok(());
+        }
+    }
+
+    if args.raw {
+        println!("{}", total_size);
+        return Ok(());
+    }
+
+    if args.verbose {
+        for entry in &merged.entries {
+            println!(
+                "{}: {}",
+                entry.path.display().to_string().label(),
+                size_format.format_field(entry.size).good()
+            );
+        }
+    }
+
+    if !merged.conflicts.is_empty() {
+        println!("\n{}", "--- Conflicts ---".value().bold());
+        for conflict in &merged.conflicts {
+            let versions: Vec<String> = conflict
+                .versions
+                .iter()
+                .map(|(snapshot, bytes)| {
+                    format!("{} in {}", size_format.format(*bytes), snapshot.display())
+                })
+                .collect();
+            println!("{}: {}", conflict.path.label(), versions.join(", "));
+        }
+    }
+
+    if let Some((group_by, groups)) = args.groups(&merged.entries, extension_rules) {
+        print_groups(group_by, &groups, size_format, args.group_warn_over);
+    }
 
+    println!("\n{}", "--- Summary ---".value().bold());
+    println!(
+        "{}: {}",
+        "Snapshots merged".good(),
+        args.merge.len().to_string().value().bold()
+    );
+    println!(
+        "{}: {}",
+        "Files".good(),
+        merged.entries.len().to_string().value().bold()
+    );
+    if !merged.conflicts.is_empty() {
+        println!(
+            "{}: {}",
+            "Conflicting paths".warn(),
+            merged.conflicts.len().to_string().warn()
+        );
+    }
+    println!(
+        "{}: {}",
+        "Total size".good().bold(),
+        size_format.format_total(total_size).total().bold()
+    );
     Ok(())
 }
 
-fn format_size(size: u64) -> String {
-    const UNITS: &[(&str, &str)] = &[
-        ("B", "bright_white"),
-        ("KB", "bright_blue"),
-        ("MB", "bright_green"),
-        ("GB", "bright_yellow"),
-        ("TB", "bright_red"),
-    ];
-    let mut size = size as f64;
-    let mut unit_index = 0;
+/// What measuring one path produced.
+enum Measured {
+    File(FileEntry),
+    /// The file vanished before it was measured, with `--allow-missing`.
+    Missing,
+    /// A metadata-based filter left the file out.
+    Filtered,
+}
 
-    while size >= 1024.0 && unit_index < UNITS.len() - 1 {
-        size /= 1024.0;
-        unit_index += 1;
+/// Reads a file's metadata and applies the metadata-based filters. The path
+/// is moved into the resulting entry rather than copied.
+fn measure(
+    owned_path: PathBuf,
+    args: &Args,
+    user: Option<u32>,
+    extension_rules: &ext::ExtensionRules,
+) -> Result<Measured> {
+    let path = owned_path.as_path();
+    // With --skip-symlinked-dirs a directory symlink stands for itself: it is
+    // measured at the link's own size and its target's contents are not read.
+    let dir_link = args.skip_symlinked_dirs && walk::is_dir_symlink(path);
+    let metadata = if dir_link {
+        fs::symlink_metadata(longpath::fs_path(path))
+    } else {
+        fs::metadata(longpath::fs_path(path))
+    };
+    let metadata = match metadata {
+        Err(e) if args.allow_missing && e.kind() == io::ErrorKind::NotFound => {
+            return Ok(Measured::Missing);
+        }
+        metadata => {
+            metadata.with_context(|| format!("Failed to read metadata for: {}", path.display()))?
+        }
+    };
+
+    if !passes_filters(path, &metadata, args, user, extension_rules)? {
+        return Ok(Measured::Filtered);
     }
+    let uid = owner::uid(&metadata);
+
+    let checksum = args
+        .checksum
+        .filter(|_| !dir_link)
+        .map(|algorithm| checksum::hash_file(path, algorithm))
+        .transpose()?;
+    let compressed = (args.estimate_compressed && !dir_link)
+        .then(|| compress::estimate(path, metadata.len()))
+        .transpose()?;
+    let (id, is_symlink) = if args.aggregate_symlinks {
+        let is_symlink = fs::symlink_metadata(longpath::fs_path(path))
+            .map(|link| link.file_type().is_symlink())
+            .unwrap_or(false);
+        (dedup::file_id(path, &metadata), is_symlink)
+    } else if args.hardlink_stats {
+        (dedup::file_id(path, &metadata), false)
+    } else {
+        (None, false)
+    };
+    let sniffed_ext = if args.ext_from_magic
+        && !dir_link
+        && (args.force_magic || extension_rules.extension(path).is_none())
+    {
+        magic::sniff(path)
+    } else {
+        None
+    };
+    let links = args
+        .hardlink_stats
+        .then(|| dedup::link_count(&metadata))
+        .flatten();
 
-    let (unit, _color) = UNITS[unit_index];
+    Ok(Measured::File(FileEntry {
+        path: owned_path,
+        size: metadata.len(),
+        checksum,
+        id,
+        is_symlink,
+        uid,
+        device: mounts::device(&metadata),
+        mtime: metadata.modified().ok(),
+        compressed,
+        links,
+        sniffed_ext,
+    }))
+}
+
+/// Whether the file at `path` passes --user, --filter, the size bounds and
+/// the access and creation time bounds.
+fn passes_filters(
+    path: &Path,
+    metadata: &fs::Metadata,
+    args: &Args,
+    user: Option<u32>,
+    extension_rules: &ext::ExtensionRules,
+) -> Result<bool> {
+    let uid = owner::uid(metadata);
+    if user.is_some() && uid != user {
+        return Ok(false);
+    }
 
-    if unit_index == 0 {
-        format!("{} {}", size as u64, unit)
+    if let Some(filter) = &args.filter
+        && !filter.matches(path, metadata, extension_rules)?
+    {
+        return Ok(false);
+    }
+
+    if args
+        .exclude_larger_than
+        .is_some_and(|limit| metadata.len() > limit)
+        || args
+            .exclude_smaller_than
+            .is_some_and(|limit| metadata.len() < limit)
+    {
+        return Ok(false);
+    }
+
+    let accessed = timefilter::TimeBounds {
+        after: args.accessed_after,
+        before: args.accessed_before,
+    };
+    if accessed.is_set() {
+        let time = metadata.accessed().with_context(|| {
+            format!(
+                "Access time is not available for: {} (the filesystem may not record it)",
+                path.display()
+            )
+        })?;
+        if !accessed.contains(time) {
+            return Ok(false);
+        }
+    }
+
+    let created = timefilter::TimeBounds {
+        after: args.created_after,
+        before: args.created_before,
+    };
+    if created.is_set() {
+        let time = metadata.created().with_context(|| {
+            format!(
+                "Creation time is not available for: {} (the filesystem may not record it)",
+                path.display()
+            )
+        })?;
+        if !created.contains(time) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn add_size(total: u64, size: u64) -> Result<u64> {
+    total.checked_add(size).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Total size overflowed u64 while adding {} bytes to {} bytes",
+            size,
+            total
+        )
+    })
+}
+
+/// Writes `report` split by top-level directory into `dir`, one report per
+/// directory plus an index. Errors cannot be tied to a directory, so they
+/// are only counted in the index. Fails before writing anything when two
+/// directories would share a file name, even one differing only in case.
+fn write_shards(
+    args: &Args,
+    dir: &Path,
+    report: &report::Report,
+    rules: &ext::ExtensionRules,
+) -> Result<()> {
+    let index = report::index_file_name(report.format);
+    let partitions = groups::partition(report.entries, groups::GroupBy::Dir(1), rules);
+    let mut names: HashMap<String, String> = HashMap::new();
+    names.insert(index.to_lowercase(), "the index".to_string());
+    for directory in partitions.keys() {
+        let file = report::shard_file_name(directory, report.format);
+        if let Some(other) = names.insert(file.to_lowercase(), format!("'{}'", directory)) {
+            anyhow::bail!(
+                "--shard-output would write both {} and '{}' to {}",
+                other,
+                directory,
+                file
+            );
+        }
+    }
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create shard directory: {}", dir.display()))?;
+
+    let mut shards = Vec::new();
+    for (directory, entries) in partitions {
+        let file = report::shard_file_name(&directory, report.format);
+        let mut total_bytes = 0u64;
+        for entry in &entries {
+            total_bytes = add_size(total_bytes, entry.size)?;
+        }
+        let shard = report::Report {
+            format: report.format,
+            entries: &entries,
+            totals: report::Totals {
+                total_bytes,
+                file_count: entries.len(),
+                error_count: 0,
+            },
+            run: report::RunInfo::new(&args.patterns, args.deterministic),
+            encoding: report.encoding,
+            groups: args.groups(&entries, rules),
+            compact: report.compact,
+            units_in_header: report.units_in_header,
+        };
+        let (mut writer, _) = report::open_destination(Some(&dir.join(&file)), false)?;
+        shard.write(&mut writer, false, false)?;
+        shards.push(report::Shard {
+            directory,
+            file,
+            file_count: shard.totals.file_count,
+            bytes: shard.totals.total_bytes,
+        });
+    }
+
+    let (mut writer, _) = report::open_destination(Some(&dir.join(index)), false)?;
+    report::write_index(
+        &mut writer,
+        report.format,
+        &report.run,
+        &report.totals,
+        &shards,
+        report.compact,
+        report.units_in_header,
+    )
+}
+
+fn percent_of(part: u64, whole: u64) -> String {
+    if whole == 0 {
+        "0%".to_string()
     } else {
-        format!("{:.2} {}", size, unit)
+        format!("{:.0}%", part as f64 / whole as f64 * 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_size_reaches_u64_max() {
+        assert_eq!(add_size(u64::MAX - 1, 1).unwrap(), u64::MAX);
+        assert_eq!(add_size(0, u64::MAX).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn add_size_fails_instead_of_wrapping() {
+        let err = add_size(u64::MAX, 1).unwrap_err();
+        assert!(err.to_string().contains("overflowed"), "{}", err);
+        assert!(add_size(u64::MAX / 2 + 1, u64::MAX / 2 + 1).is_err());
     }
 }
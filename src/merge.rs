@@ -0,0 +1,71 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::FileEntry;
+use crate::snapshot::Snapshot;
+
+/// A path recorded with different sizes by different snapshots.
+pub struct Conflict {
+    pub path: String,
+    /// Each distinct size with the first snapshot that recorded it.
+    pub versions: Vec<(PathBuf, u64)>,
+}
+
+pub struct Merged {
+    pub entries: Vec<FileEntry>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Combines several snapshots into one file list. A path recorded with the
+/// same size everywhere counts once. A path whose size differs counts once
+/// per distinct size and is reported as a conflict.
+/// With `qualify`, every path is prefixed with its snapshot's file stem, as
+/// in `host-a:src/main.rs`, so nothing is merged at all.
+pub fn merge(snapshots: &[PathBuf], qualify: bool) -> Result<Merged> {
+    let mut versions: BTreeMap<String, Vec<(&Path, u64)>> = BTreeMap::new();
+    for snapshot_path in snapshots {
+        let snapshot = Snapshot::load(snapshot_path)?;
+        for file in snapshot.files {
+            let path = if qualify {
+                format!("{}:{}", stem(snapshot_path), file.path)
+            } else {
+                file.path
+            };
+            versions
+                .entry(path)
+                .or_default()
+                .push((snapshot_path, file.bytes));
+        }
+    }
+
+    let mut entries = Vec::new();
+    let mut conflicts = Vec::new();
+    for (path, mut found) in versions {
+        let mut sizes = HashSet::new();
+        found.retain(|(_, bytes)| sizes.insert(*bytes));
+        if let [(_, bytes)] = found.as_slice() {
+            entries.push(FileEntry::recorded(PathBuf::from(&path), *bytes));
+            continue;
+        }
+
+        for (_, bytes) in &found {
+            entries.push(FileEntry::recorded(PathBuf::from(&path), *bytes));
+        }
+        conflicts.push(Conflict {
+            path,
+            versions: found
+                .into_iter()
+                .map(|(snapshot_path, bytes)| (snapshot_path.to_path_buf(), bytes))
+                .collect(),
+        });
+    }
+
+    Ok(Merged { entries, conflicts })
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::fs::Metadata;
+
+/// The id of the device, and so the filesystem, a file lives on.
+#[cfg(unix)]
+pub fn device(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+pub fn device(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+pub fn supported() -> bool {
+    cfg!(unix)
+}
+
+/// Where each device is mounted, read from `/proc/self/mountinfo`. A device
+/// mounted in several places, e.g. through bind mounts, maps to its shortest
+/// mount point. Empty where the file does not exist, as outside Linux.
+pub fn mount_points() -> HashMap<u64, String> {
+    let mut points: HashMap<u64, String> = HashMap::new();
+    let Ok(mountinfo) = std::fs::read_to_string("/proc/self/mountinfo") else {
+        return points;
+    };
+
+    // Each line is "<id> <parent> <major>:<minor> <root> <mount point> ...".
+    for line in mountinfo.lines() {
+        let mut fields = line.split(' ').skip(2);
+        let (Some(device), Some(point)) = (fields.next(), fields.nth(1)) else {
+            continue;
+        };
+        let Some(device) = parse_device(device) else {
+            continue;
+        };
+        let point = unescape(point);
+        match points.get(&device) {
+            Some(known) if known.len() <= point.len() => {}
+            _ => {
+                points.insert(device, point);
+            }
+        }
+    }
+    points
+}
+
+/// The label of a device: its mount point, or its id when that is unknown.
+pub fn label(device: u64, points: &HashMap<u64, String>) -> String {
+    points
+        .get(&device)
+        .cloned()
+        .unwrap_or_else(|| format!("device {}", device))
+}
+
+#[cfg(target_os = "linux")]
+fn parse_device(field: &str) -> Option<u64> {
+    let (major, minor) = field.split_once(':')?;
+    Some(libc::makedev(major.parse().ok()?, minor.parse().ok()?))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn parse_device(_field: &str) -> Option<u64> {
+    None
+}
+
+/// Undoes the octal escapes (`\040` for a space) mountinfo uses in paths.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 4).filter(|_| bytes[i] == b'\\');
+        match escape
+            .and_then(|digits| u8::from_str_radix(std::str::from_utf8(digits).ok()?, 8).ok())
+        {
+            Some(byte) => {
+                out.push(byte);
+                i += 4;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
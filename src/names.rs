@@ -0,0 +1,27 @@
+use clap::ValueEnum;
+
+use crate::FileEntry;
+
+/// Which part of each path `--count-names` charges for.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum NameCost {
+    /// The file name alone, as stored in its directory entry
+    Name,
+    /// The whole path as matched
+    Path,
+}
+
+/// The bytes taken by the names of all `entries`, counting the raw
+/// platform encoding of each name. Nothing is read from disk.
+pub fn overhead(entries: &[FileEntry], cost: NameCost) -> u64 {
+    entries
+        .iter()
+        .map(|entry| {
+            let name = match cost {
+                NameCost::Name => entry.path.file_name().unwrap_or_default(),
+                NameCost::Path => entry.path.as_os_str(),
+            };
+            name.len() as u64
+        })
+        .sum()
+}
@@ -0,0 +1,72 @@
+use colored::*;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::FileEntry;
+use crate::checksum;
+use crate::theme::Themed;
+
+/// Files that are probably copies of each other: same size and same
+/// sampled content.
+pub struct NearDupGroup {
+    pub size: u64,
+    pub paths: Vec<PathBuf>,
+}
+
+impl NearDupGroup {
+    /// Bytes freed by keeping only one of the files.
+    pub fn reclaimable(&self) -> u64 {
+        self.size
+            .saturating_mul(self.paths.len().saturating_sub(1) as u64)
+    }
+}
+
+/// Groups files by size, then by a hash of samples of their content, and
+/// returns the groups with more than one file, most reclaimable first.
+/// Only files sharing a size with another file are read, and empty files are
+/// ignored. Files that cannot be sampled are skipped with a warning.
+pub fn find(entries: &[FileEntry]) -> Vec<NearDupGroup> {
+    let mut by_size: HashMap<u64, Vec<&FileEntry>> = HashMap::new();
+    for entry in entries.iter().filter(|entry| entry.size > 0) {
+        by_size.entry(entry.size).or_default().push(entry);
+    }
+
+    let candidates: Vec<&FileEntry> = by_size
+        .into_values()
+        .filter(|same_size| same_size.len() > 1)
+        .flatten()
+        .collect();
+    let sampled: Vec<((u64, u64), PathBuf)> = candidates
+        .par_iter()
+        .filter_map(
+            |entry| match checksum::sample_hash(&entry.path, entry.size) {
+                Ok(hash) => Some(((entry.size, hash), entry.path.clone())),
+                Err(e) => {
+                    eprintln!("{}: {:#}", "Warning".warn().bold(), e);
+                    None
+                }
+            },
+        )
+        .collect();
+
+    let mut by_sample: HashMap<(u64, u64), Vec<PathBuf>> = HashMap::new();
+    for (key, path) in sampled {
+        by_sample.entry(key).or_default().push(path);
+    }
+
+    let mut groups: Vec<NearDupGroup> = by_sample
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|((size, _), mut paths)| {
+            paths.sort();
+            NearDupGroup { size, paths }
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.reclaimable()
+            .cmp(&a.reclaimable())
+            .then_with(|| a.paths.cmp(&b.paths))
+    });
+    groups
+}
@@ -0,0 +1,106 @@
+use std::fmt::Display;
+use std::io::{self, BufWriter, Stdout, Write};
+use std::sync::Mutex;
+
+/// Single sink for lines produced while files are processed, such as debug
+/// traces and per-file results. Every line is written whole under one lock,
+/// so output produced from rayon workers never interleaves. Flush it before
+/// printing anything else to stdout.
+///
+/// Lines are block-buffered for throughput unless `flush_each` is set, in
+/// which case every line reaches stdout as soon as it is written.
+pub struct Output<W: Write = Stdout> {
+    writer: Mutex<BufWriter<W>>,
+    flush_each: bool,
+}
+
+impl Output {
+    pub fn new(flush_each: bool) -> Self {
+        Self::to(io::stdout(), flush_each)
+    }
+}
+
+impl<W: Write> Output<W> {
+    /// An output writing to `writer` instead of stdout.
+    pub fn to(writer: W, flush_each: bool) -> Self {
+        Self {
+            writer: Mutex::new(BufWriter::new(writer)),
+            flush_each,
+        }
+    }
+
+    pub fn line(&self, line: impl Display) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(writer, "{}", line)?;
+        if self.flush_each {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.writer
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::prelude::*;
+    use std::sync::Arc;
+
+    /// Collects everything written, one `write` call at a time.
+    #[derive(Clone, Default)]
+    struct Collected(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Collected {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn stress(flush_each: bool) {
+        let collected = Collected::default();
+        let output = Output::to(collected.clone(), flush_each);
+        // Long lines with a distinct fill per line, so a torn write shows.
+        (0..2000).into_par_iter().for_each(|i| {
+            let fill = char::from(b'a' + (i % 26) as u8);
+            output
+                .line(format!("{:04} {}", i, fill.to_string().repeat(300)))
+                .unwrap();
+        });
+        output.flush().unwrap();
+
+        let text = String::from_utf8(collected.0.lock().unwrap().clone()).unwrap();
+        let mut seen: Vec<usize> = text
+            .lines()
+            .map(|line| {
+                let (index, body) = line.split_once(' ').unwrap();
+                let index: usize = index.parse().unwrap();
+                let fill = char::from(b'a' + (index % 26) as u8);
+                assert_eq!(body, fill.to_string().repeat(300), "torn line {}", index);
+                index
+            })
+            .collect();
+        seen.sort_unstable();
+        assert_eq!(seen, (0..2000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn parallel_lines_do_not_interleave() {
+        stress(false);
+    }
+
+    #[test]
+    fn parallel_lines_do_not_interleave_when_flushed_each() {
+        stress(true);
+    }
+}
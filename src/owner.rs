@@ -0,0 +1,46 @@
+use anyhow::Result;
+use std::fs::Metadata;
+
+#[cfg(unix)]
+pub fn uid(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.uid())
+}
+
+#[cfg(not(unix))]
+pub fn uid(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+/// Resolves a uid to its user name, falling back to the numeric id.
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> String {
+    users::get_user_by_uid(uid)
+        .map(|user| user.name().to_string_lossy().into_owned())
+        .unwrap_or_else(|| uid.to_string())
+}
+
+#[cfg(not(unix))]
+pub fn user_name(uid: u32) -> String {
+    uid.to_string()
+}
+
+/// Accepts either a user name or a numeric uid.
+#[cfg(unix)]
+pub fn lookup_uid(name: &str) -> Result<u32> {
+    if let Some(user) = users::get_user_by_name(name) {
+        return Ok(user.uid());
+    }
+    name.parse()
+        .map_err(|_| anyhow::anyhow!("Unknown user: {}", name))
+}
+
+#[cfg(not(unix))]
+pub fn lookup_uid(name: &str) -> Result<u32> {
+    anyhow::bail!("Filtering by user ({}) is only supported on Unix", name)
+}
+
+pub fn supported() -> bool {
+    cfg!(unix)
+}
@@ -0,0 +1,303 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{self, BufRead};
+use std::path::{Component, Path, PathBuf};
+
+use crate::display::pattern_root;
+
+/// What each line read from stdin stands for.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StdinMode {
+    /// Glob patterns, expanded like the ones given as arguments. Blank lines
+    /// and lines starting with `#` are skipped
+    Patterns,
+    /// Literal paths, matched as-is even if they contain glob characters.
+    /// Blank lines are skipped
+    Paths,
+}
+
+/// Reads patterns from stdin, one per line. Literal paths are escaped so the
+/// rest of the pipeline can treat every line as a pattern.
+pub fn read_stdin(mode: StdinMode) -> Result<Vec<String>> {
+    let mut patterns = Vec::new();
+
+    for line in io::stdin().lock().lines() {
+        let line = line.context("Failed to read patterns from stdin")?;
+        match mode {
+            StdinMode::Patterns => {
+                let line = line.trim();
+                if !line.is_empty() && !line.starts_with('#') {
+                    patterns.push(line.to_string());
+                }
+            }
+            StdinMode::Paths => {
+                if !line.is_empty() {
+                    patterns.push(escape_literal(&line));
+                }
+            }
+        }
+    }
+
+    Ok(patterns)
+}
+
+/// Whether `pattern` has no wildcards and so names a single path.
+pub fn is_literal(pattern: &str) -> bool {
+    !pattern.contains(['*', '?', '['])
+}
+
+/// Escapes glob and brace characters so `path` only matches itself.
+fn escape_literal(path: &str) -> String {
+    glob::Pattern::escape(path)
+        .replace('{', "\\{")
+        .replace('}', "\\}")
+}
+
+/// Decides which matches of a pattern are hidden files that wildcards should
+/// not pick up: paths with a component starting with a dot below the
+/// pattern's literal root. A pattern that spells out a leading dot in any
+/// component, like `**/.*` or `.config/*`, may match hidden names.
+pub struct HiddenFilter {
+    root: Option<PathBuf>,
+}
+
+impl HiddenFilter {
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            root: (!names_hidden(pattern)).then(|| pattern_root(pattern)),
+        }
+    }
+
+    pub fn is_hidden(&self, path: &Path) -> bool {
+        let Some(root) = &self.root else {
+            return false;
+        };
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .components()
+            .any(is_hidden)
+    }
+}
+
+/// Whether `pattern` spells out a leading dot in any component, and so may
+/// match hidden names.
+pub fn names_hidden(pattern: &str) -> bool {
+    Path::new(pattern).components().any(is_hidden)
+}
+
+fn is_hidden(component: Component) -> bool {
+    matches!(component, Component::Normal(name) if name.as_encoded_bytes().starts_with(b"."))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Token {
+    Char(char),
+    Escaped(char),
+}
+
+/// Expands shell-style brace groups, so `**/*.{png,jpg}` becomes `**/*.png`
+/// and `**/*.jpg`. Groups may be nested, and `\{`, `\}` and `\,` stand for
+/// the literal characters. A group without a top-level comma or without a
+/// closing brace is kept as-is, matching bash.
+pub fn expand_braces(pattern: &str) -> Vec<String> {
+    expand(&tokenize(pattern))
+        .into_iter()
+        .map(|tokens| {
+            tokens
+                .into_iter()
+                .map(|token| match token {
+                    Token::Char(c) | Token::Escaped(c) => c,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn tokenize(pattern: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next)) if matches!(next, '{' | '}' | ',') => {
+                tokens.push(Token::Escaped(next));
+                chars.next();
+            }
+            _ => tokens.push(Token::Char(c)),
+        }
+    }
+
+    tokens
+}
+
+fn expand(tokens: &[Token]) -> Vec<Vec<Token>> {
+    for open in 0..tokens.len() {
+        if tokens[open] != Token::Char('{') {
+            continue;
+        }
+
+        let Some((close, commas)) = find_group(tokens, open) else {
+            continue;
+        };
+        if commas.is_empty() {
+            continue;
+        }
+
+        let prefix = &tokens[..open];
+        let suffix = &tokens[close + 1..];
+        let mut bounds = vec![open];
+        bounds.extend(&commas);
+        bounds.push(close);
+
+        return bounds
+            .windows(2)
+            .flat_map(|bound| {
+                let mut candidate = prefix.to_vec();
+                candidate.extend_from_slice(&tokens[bound[0] + 1..bound[1]]);
+                candidate.extend_from_slice(suffix);
+                expand(&candidate)
+            })
+            .collect();
+    }
+
+    vec![tokens.to_vec()]
+}
+
+/// Finds the brace closing the group opened at `open`, along with the
+/// positions of the commas that separate its top-level alternatives.
+fn find_group(tokens: &[Token], open: usize) -> Option<(usize, Vec<usize>)> {
+    let mut depth = 0;
+    let mut commas = Vec::new();
+
+    for (index, token) in tokens.iter().enumerate().skip(open) {
+        match token {
+            Token::Char('{') => depth += 1,
+            Token::Char('}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((index, commas));
+                }
+            }
+            Token::Char(',') if depth == 1 => commas.push(index),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// What one pattern contributed to the deduplicated file list.
+pub struct Attribution {
+    /// Files the pattern matched.
+    pub matches: usize,
+    /// Files no other pattern matched.
+    pub unique: usize,
+    /// Files at least one other pattern also matched.
+    pub overlap: usize,
+    /// Files attributed to this pattern because it is the first one, in
+    /// command-line order, that matched them.
+    pub won: usize,
+}
+
+/// Attributes every kept file to the patterns in `sources`, the indices of
+/// the patterns that matched each path. A path listed several times, once
+/// per pattern that matched it, is attributed once. Returns one row per
+/// pattern.
+pub fn attribute(
+    pattern_count: usize,
+    files: &[PathBuf],
+    sources: &HashMap<PathBuf, BTreeSet<usize>>,
+) -> Vec<Attribution> {
+    let mut rows: Vec<Attribution> = (0..pattern_count)
+        .map(|_| Attribution {
+            matches: 0,
+            unique: 0,
+            overlap: 0,
+            won: 0,
+        })
+        .collect();
+
+    let mut seen = HashSet::new();
+    for path in files {
+        if !seen.insert(path) {
+            continue;
+        }
+        let Some(indices) = sources.get(path) else {
+            continue;
+        };
+        for &index in indices {
+            let row = &mut rows[index];
+            row.matches += 1;
+            if indices.len() == 1 {
+                row.unique += 1;
+            } else {
+                row.overlap += 1;
+            }
+        }
+        if let Some(&first) = indices.first() {
+            rows[first].won += 1;
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_groups_expand_in_order() {
+        assert_eq!(expand_braces("a{b,c{d,e}}f"), ["abf", "acdf", "acef"]);
+        assert_eq!(expand_braces("*.{png,jpg}"), ["*.png", "*.jpg"]);
+    }
+
+    #[test]
+    fn escaped_braces_and_commas_are_literal() {
+        assert_eq!(expand_braces("\\{a,b\\}"), ["{a,b}"]);
+        assert_eq!(expand_braces("{a\\,b,c}"), ["a,b", "c"]);
+    }
+
+    #[test]
+    fn groups_without_a_comma_or_closing_brace_are_kept() {
+        assert_eq!(expand_braces("{a}"), ["{a}"]);
+        assert_eq!(expand_braces("{a,b"), ["{a,b"]);
+        assert_eq!(expand_braces("x{a,b"), ["x{a,b"]);
+    }
+
+    #[test]
+    fn empty_alternatives_expand_to_nothing() {
+        assert_eq!(expand_braces("{,x}.rs"), [".rs", "x.rs"]);
+    }
+
+    #[test]
+    fn wildcards_skip_hidden_names() {
+        let filter = HiddenFilter::new("**/*");
+        assert!(filter.is_hidden(Path::new(".env")));
+        assert!(filter.is_hidden(Path::new(".git/config")));
+        assert!(filter.is_hidden(Path::new("src/.cache/a.rs")));
+        assert!(!filter.is_hidden(Path::new("src/main.rs")));
+    }
+
+    #[test]
+    fn a_spelled_out_dot_matches_hidden_names() {
+        for pattern in ["**/.*", ".config/*", "src/.cache/*.rs"] {
+            assert!(names_hidden(pattern), "{}", pattern);
+            assert!(!HiddenFilter::new(pattern).is_hidden(Path::new(".config/a")));
+        }
+        assert!(!names_hidden("**/*.rs"));
+        assert!(!names_hidden("./src/*"));
+        assert!(!names_hidden("../src/*"));
+    }
+
+    #[test]
+    fn hidden_roots_do_not_hide_their_contents() {
+        // Only components below the pattern's literal root count.
+        let filter = HiddenFilter {
+            root: Some(PathBuf::from("/home/me/.local/share")),
+        };
+        assert!(!filter.is_hidden(Path::new("/home/me/.local/share/file")));
+        assert!(filter.is_hidden(Path::new("/home/me/.local/share/.cache/file")));
+        assert!(filter.is_hidden(Path::new("/home/me/.local/other/file")));
+    }
+}
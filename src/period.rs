@@ -0,0 +1,66 @@
+use clap::ValueEnum;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::timefilter::civil_from_days;
+
+/// A calendar period that modification times are bucketed into.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Period {
+    Day,
+    /// Weeks start on Monday and are labelled by that date
+    Week,
+    Month,
+    Year,
+}
+
+impl Period {
+    /// The label of the period containing `time`, e.g. `2024-03-15`,
+    /// `2024-03-11` (the Monday of that week), `2024-03` or `2024`. Labels
+    /// sort chronologically. Local time is used unless `utc` is set.
+    pub fn label(self, time: SystemTime, utc: bool) -> String {
+        let seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_secs() as i64,
+            Err(before) => -(before.duration().as_secs() as i64),
+        };
+        let offset = if utc { 0 } else { local_offset(seconds) };
+        let days = (seconds + offset).div_euclid(86_400);
+
+        match self {
+            Period::Day => {
+                let (year, month, day) = civil_from_days(days);
+                format!("{:04}-{:02}-{:02}", year, month, day)
+            }
+            Period::Week => {
+                // 1970-01-01 was a Thursday, three days after a Monday.
+                let monday = days - (days + 3).rem_euclid(7);
+                let (year, month, day) = civil_from_days(monday);
+                format!("{:04}-{:02}-{:02}", year, month, day)
+            }
+            Period::Month => {
+                let (year, month, _) = civil_from_days(days);
+                format!("{:04}-{:02}", year, month)
+            }
+            Period::Year => format!("{:04}", civil_from_days(days).0),
+        }
+    }
+}
+
+/// Seconds east of UTC of the local time zone at the given Unix time.
+#[cfg(unix)]
+fn local_offset(seconds: i64) -> i64 {
+    let time = seconds as libc::time_t;
+    // SAFETY: `localtime_r` only writes into the `tm` we pass it.
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&time, &mut tm).is_null() {
+            0
+        } else {
+            tm.tm_gmtoff as i64
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn local_offset(_seconds: i64) -> i64 {
+    0
+}
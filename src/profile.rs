@@ -0,0 +1,74 @@
+use colored::*;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::size::SizeFormat;
+use crate::theme::Themed;
+
+#[derive(Default)]
+struct ThreadStats {
+    files: usize,
+    bytes: u64,
+    busy: Duration,
+}
+
+/// Per-worker accounting for the metadata stage. Each rayon worker only ever
+/// touches its own slot, so the locks are uncontended.
+pub struct Profiler {
+    threads: Vec<Mutex<ThreadStats>>,
+    started: Instant,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            threads: (0..rayon::current_num_threads())
+                .map(|_| Mutex::default())
+                .collect(),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn record(&self, bytes: u64, busy: Duration) {
+        let index = rayon::current_thread_index().unwrap_or(0);
+        if let Some(slot) = self.threads.get(index) {
+            let mut stats = slot.lock().unwrap_or_else(|e| e.into_inner());
+            stats.files += 1;
+            stats.bytes += bytes;
+            stats.busy += busy;
+        }
+    }
+
+    pub fn report(&self, size_format: &SizeFormat) {
+        let wall = self.started.elapsed();
+        let mut total_busy = Duration::ZERO;
+
+        eprintln!("\n{}", "--- Profile ---".value().bold());
+        for (index, slot) in self.threads.iter().enumerate() {
+            let stats = slot.lock().unwrap_or_else(|e| e.into_inner());
+            total_busy += stats.busy;
+            eprintln!(
+                "{}: {} files, {}, busy {:.1?}",
+                format!("Thread {}", index).label(),
+                stats.files.to_string().value(),
+                size_format.format(stats.bytes).good(),
+                stats.busy
+            );
+        }
+
+        let capacity = wall.as_secs_f64() * self.threads.len() as f64;
+        let efficiency = if capacity > 0.0 {
+            total_busy.as_secs_f64() / capacity * 100.0
+        } else {
+            0.0
+        };
+        eprintln!(
+            "{}: {:.1?} wall, {:.1?} busy across {} threads ({:.0}% parallel efficiency)",
+            "Metadata stage".good(),
+            wall,
+            total_busy,
+            self.threads.len(),
+            efficiency
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use crate::size::SizeFormat;
+
+/// Running totals of the measuring stage, for `--progress-interval`.
+#[derive(Default)]
+pub struct Progress {
+    files: AtomicUsize,
+    bytes: AtomicU64,
+}
+
+impl Progress {
+    /// Records one processed path and the bytes it added to the total.
+    pub fn record(&self, bytes: u64) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Runs `work`, logging a progress line to stderr every `interval` until
+    /// it returns. `total` is the number of paths `work` will process.
+    pub fn log_while<T>(
+        &self,
+        interval: Duration,
+        total: usize,
+        size_format: &SizeFormat,
+        work: impl FnOnce() -> T,
+    ) -> T {
+        std::thread::scope(|scope| {
+            let (done, finished) = mpsc::channel::<()>();
+            scope.spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(interval) {
+                    eprintln!(
+                        "processed {} of {} files, {} so far",
+                        self.files.load(Ordering::Relaxed),
+                        total,
+                        size_format.format(self.bytes.load(Ordering::Relaxed))
+                    );
+                }
+            });
+            let result = work();
+            drop(done);
+            result
+        })
+    }
+}
@@ -0,0 +1,498 @@
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::FileEntry;
+use crate::encoding::PathEncoding;
+use crate::groups::{Group, GroupBy};
+use crate::timefilter::format_rfc3339;
+
+/// The unit every size in machine output is written in. Reports never use
+/// human-formatted sizes, whatever the display options.
+pub const UNIT: &str = "bytes";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+impl Format {
+    /// The format matching a file's extension, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+
+    /// The name of a report format a file's extension asks for but that
+    /// cannot be written, so `--output` can reject it instead of guessing.
+    pub fn unsupported(path: &Path) -> Option<&'static str> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "tsv" => Some("TSV"),
+            "md" => Some("Markdown"),
+            "html" | "htm" => Some("HTML"),
+            _ => None,
+        }
+    }
+}
+
+/// Describes the invocation that produced a report, so results appended to
+/// one file by several runs can be told apart.
+pub struct RunInfo {
+    pub timestamp: String,
+    pub version: &'static str,
+    pub patterns: Vec<String>,
+}
+
+impl RunInfo {
+    /// With `reproducible` set, the timestamp comes from `SOURCE_DATE_EPOCH`,
+    /// or is the Unix epoch, instead of the current time.
+    pub fn new(patterns: &[String], reproducible: bool) -> Self {
+        let time = if reproducible {
+            let seconds = std::env::var("SOURCE_DATE_EPOCH")
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or(0);
+            UNIX_EPOCH + Duration::from_secs(seconds)
+        } else {
+            SystemTime::now()
+        };
+        Self {
+            timestamp: format_rfc3339(time),
+            version: env!("CARGO_PKG_VERSION"),
+            patterns: patterns.to_vec(),
+        }
+    }
+}
+
+pub struct Totals {
+    pub total_bytes: u64,
+    pub file_count: usize,
+    pub error_count: usize,
+}
+
+pub struct Report<'a> {
+    pub format: Format,
+    pub entries: &'a [FileEntry],
+    pub totals: Totals,
+    pub run: RunInfo,
+    pub encoding: PathEncoding,
+    /// The active grouping and its groups, in display order.
+    pub groups: Option<(GroupBy, Vec<Group>)>,
+    /// Write JSON on one line instead of pretty-printed.
+    pub compact: bool,
+    /// Declare the size unit: a `unit` field in JSON, a `unit` column in CSV.
+    pub units_in_header: bool,
+}
+
+/// Where a report is written: stdout, or a file that is truncated or, with
+/// `append`, extended.
+pub fn open_destination(path: Option<&Path>, append: bool) -> Result<(Box<dyn Write>, bool)> {
+    let Some(path) = path else {
+        return Ok((Box::new(io::stdout().lock()), false));
+    };
+
+    let has_content = append && path.metadata().is_ok_and(|metadata| metadata.len() > 0);
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .with_context(|| format!("Failed to open output file: {}", path.display()))?;
+    Ok((Box::new(io::BufWriter::new(file)), has_content))
+}
+
+impl Report<'_> {
+    /// Writes the report. `has_content` tells the CSV writer that it is
+    /// appending to existing rows and must not repeat the header.
+    pub fn write(&self, writer: &mut dyn Write, append: bool, has_content: bool) -> Result<()> {
+        match self.format {
+            Format::Json => self.write_json(writer),
+            Format::Csv => self.write_csv(writer, append, has_content),
+        }
+    }
+
+    /// The JSON report is one object:
+    ///
+    /// ```text
+    /// {
+    ///   "run": {"timestamp": "...", "version": "...", "patterns": ["..."]},
+    ///   "base": "/absolute/working/directory",
+    ///   "unit": "bytes",
+    ///   "total_bytes": 0, "file_count": 0, "error_count": 0,
+    ///   "grouped_by": "extension" | "directory" | "owner" | "mount" | "period",
+    ///   "groups": [{"key": "png", "count": 0, "bytes": 0}],
+    ///   "files": [{"path": "...", "bytes": 0}]
+    /// }
+    /// ```
+    ///
+    /// `base` is the directory relative `path`s are resolved against, or null
+    /// when every path is already absolute. With `--encoding base64`, paths
+    /// that are not valid UTF-8 are stored base64-encoded in `path_b64` (or
+    /// `base_b64`) instead.
+    ///
+    /// Sizes are always whole bytes. `unit` is only present with
+    /// `--units-in-header` and is then always `"bytes"`.
+    ///
+    /// `grouped_by` and `groups` are only present with a grouping option.
+    /// `groups` follows `--group-sort`, ties broken by key, with any
+    /// `(other)` group last, so the same scan always serializes the same way.
+    /// Keys are the extension (`<none>` without one), the directory truncated
+    /// to the requested depth (`.` for the top level), the owner's user name
+    /// (its uid if it has none, `<unknown>` when unavailable), the mount point
+    /// (`device <id>` when it cannot be found), or the modification period
+    /// like `2024-03` (`<unknown>` without an mtime).
+    fn write_json(&self, writer: &mut dyn Write) -> Result<()> {
+        let files: Vec<serde_json::Value> = self
+            .entries
+            .iter()
+            .map(|entry| file_json(entry, self.encoding))
+            .collect();
+
+        let (base_key, base) = match self.base() {
+            Some(base) => {
+                let (base64, base) = json_path(&base, self.encoding);
+                (if base64 { "base_b64" } else { "base" }, base.into())
+            }
+            None => ("base", serde_json::Value::Null),
+        };
+
+        let mut report = serde_json::json!({
+            "run": {
+                "timestamp": self.run.timestamp,
+                "version": self.run.version,
+                "patterns": self.run.patterns,
+            },
+            base_key: base,
+            "total_bytes": self.totals.total_bytes,
+            "file_count": self.totals.file_count,
+            "error_count": self.totals.error_count,
+        });
+        if self.units_in_header {
+            report["unit"] = UNIT.into();
+        }
+        if let Some((by, groups)) = &self.groups {
+            report["grouped_by"] = by.name().into();
+            report["groups"] = groups
+                .iter()
+                .map(|group| {
+                    serde_json::json!({
+                        "key": group.key,
+                        "count": group.count,
+                        "bytes": group.bytes,
+                    })
+                })
+                .collect();
+        }
+        report["files"] = files.into();
+
+        write_json_value(writer, &report, self.compact)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// CSV columns are `path,bytes[,unit][,checksum]`. Sizes are always whole
+    /// bytes; with `--units-in-header` every row also says so in `unit`. When
+    /// appending, every row is prefixed with the run timestamp, version and
+    /// patterns.
+    fn write_csv(&self, writer: &mut dyn Write, append: bool, has_content: bool) -> Result<()> {
+        let with_checksum = self.entries.iter().any(|entry| entry.checksum.is_some());
+
+        if !has_content {
+            let mut header = Vec::new();
+            if append {
+                header.extend(["run_timestamp", "run_version", "run_patterns"]);
+            }
+            header.extend(["path", "bytes"]);
+            if self.units_in_header {
+                header.push("unit");
+            }
+            if with_checksum {
+                header.push("checksum");
+            }
+            writeln!(writer, "{}", header.join(","))?;
+        }
+
+        let run_fields = [
+            self.run.timestamp.clone(),
+            self.run.version.to_string(),
+            self.run.patterns.join(" "),
+        ];
+        for entry in self.entries {
+            let mut row: Vec<String> = Vec::new();
+            if append {
+                row.extend(run_fields.iter().cloned());
+            }
+            row.push(self.encoding.encode(&entry.path));
+            row.push(entry.size.to_string());
+            if self.units_in_header {
+                row.push(UNIT.to_string());
+            }
+            if with_checksum {
+                row.push(entry.checksum.clone().unwrap_or_default());
+            }
+            let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+            writeln!(writer, "{}", row.join(","))?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// The working directory, unless every path is already absolute.
+    fn base(&self) -> Option<PathBuf> {
+        if self.entries.iter().all(|entry| entry.path.is_absolute()) {
+            return None;
+        }
+        std::env::current_dir().ok()
+    }
+}
+
+/// One per-directory report written by `--shard-output`.
+pub struct Shard {
+    pub directory: String,
+    pub file: String,
+    pub file_count: usize,
+    pub bytes: u64,
+}
+
+/// Writes the index of a `--shard-output` directory. In JSON it carries the
+/// run, the overall totals and a `shards` array; in CSV it is one
+/// `directory,file,file_count,bytes[,unit]` row per shard. `units_in_header`
+/// declares the unit as in [`Report`].
+pub fn write_index(
+    writer: &mut dyn Write,
+    format: Format,
+    run: &RunInfo,
+    totals: &Totals,
+    shards: &[Shard],
+    compact: bool,
+    units_in_header: bool,
+) -> Result<()> {
+    match format {
+        Format::Json => {
+            let mut index = serde_json::json!({
+                "run": {
+                    "timestamp": run.timestamp,
+                    "version": run.version,
+                    "patterns": run.patterns,
+                },
+                "total_bytes": totals.total_bytes,
+                "file_count": totals.file_count,
+                "error_count": totals.error_count,
+                "shards": shards
+                    .iter()
+                    .map(|shard| {
+                        serde_json::json!({
+                            "directory": shard.directory,
+                            "file": shard.file,
+                            "file_count": shard.file_count,
+                            "bytes": shard.bytes,
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+            });
+            if units_in_header {
+                index["unit"] = UNIT.into();
+            }
+            write_json_value(writer, &index, compact)?;
+        }
+        Format::Csv => {
+            let unit = if units_in_header {
+                format!(",{}", UNIT)
+            } else {
+                String::new()
+            };
+            let header_unit = if units_in_header { ",unit" } else { "" };
+            writeln!(writer, "directory,file,file_count,bytes{}", header_unit)?;
+            for shard in shards {
+                writeln!(
+                    writer,
+                    "{},{},{},{}{}",
+                    csv_field(&shard.directory),
+                    csv_field(&shard.file),
+                    shard.file_count,
+                    shard.bytes,
+                    unit
+                )?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// The name of the shard file for a top-level directory: `.` becomes `_top`
+/// and path separators become `_`. Names starting with `_` are reserved for
+/// `_top` and the index, so a directory whose name starts with `_` gets one
+/// more in front: `_index` becomes `__index.json`.
+pub fn shard_file_name(directory: &str, format: Format) -> String {
+    let stem = if directory == "." {
+        "_top".to_string()
+    } else {
+        let stem = directory.replace(['/', '\\'], "_");
+        if stem.starts_with('_') {
+            format!("_{}", stem)
+        } else {
+            stem
+        }
+    };
+    format!("{}.{}", stem, shard_extension(format))
+}
+
+/// The name of the index file of a `--shard-output` directory.
+pub fn index_file_name(format: Format) -> String {
+    format!("_index.{}", shard_extension(format))
+}
+
+fn shard_extension(format: Format) -> &'static str {
+    match format {
+        Format::Json => "json",
+        Format::Csv => "csv",
+    }
+}
+
+/// The JSON object describing one file, shared by `--json` and `--json-stream`.
+pub fn file_json(entry: &FileEntry, encoding: PathEncoding) -> serde_json::Value {
+    let (base64, path) = json_path(&entry.path, encoding);
+    let mut object = serde_json::json!({
+        if base64 { "path_b64" } else { "path" }: path,
+        "bytes": entry.size,
+    });
+    if let Some(hash) = &entry.checksum {
+        object["checksum"] = hash.clone().into();
+    }
+    object
+}
+
+/// The path as JSON text, and whether it is base64. Only paths that are not
+/// valid UTF-8 are base64-encoded in JSON.
+fn json_path(path: &Path, encoding: PathEncoding) -> (bool, String) {
+    match path.to_str() {
+        Some(text) if encoding == PathEncoding::Base64 => (false, text.to_string()),
+        None if encoding == PathEncoding::Base64 => (true, encoding.encode(path)),
+        _ => (false, encoding.encode(path)),
+    }
+}
+
+fn write_json_value(
+    writer: &mut dyn Write,
+    value: &serde_json::Value,
+    compact: bool,
+) -> Result<()> {
+    if compact {
+        serde_json::to_writer(&mut *writer, value)?;
+    } else {
+        serde_json::to_writer_pretty(&mut *writer, value)?;
+    }
+    writeln!(writer)?;
+    Ok(())
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_groups_keep_their_order() {
+        use crate::ext::ExtensionRules;
+        use crate::groups::{self, GroupSort};
+
+        // Every extension ties with another on size, so only the key
+        // tie-break keeps the order stable across hash map iterations.
+        let entries: Vec<FileEntry> = [
+            ("a.txt", 3),
+            ("b.md", 3),
+            ("c.rs", 2),
+            ("d.toml", 2),
+            ("e.json", 1),
+            ("f.lock", 1),
+        ]
+        .into_iter()
+        .map(|(path, size)| FileEntry::recorded(PathBuf::from(path), size))
+        .collect();
+        let rules = ExtensionRules::new(None, None, false).unwrap();
+
+        let write = || {
+            let mut groups = groups::group(&entries, GroupBy::Extension, &rules);
+            let other = groups::fold_small(&mut groups, Some(4), None);
+            groups::sort_groups(&mut groups, GroupSort::Size, false);
+            groups.extend(other);
+            let report = Report {
+                format: Format::Json,
+                entries: &[],
+                totals: Totals {
+                    total_bytes: 12,
+                    file_count: 6,
+                    error_count: 0,
+                },
+                run: RunInfo::new(&[], true),
+                encoding: PathEncoding::Lossy,
+                groups: Some((GroupBy::Extension, groups)),
+                compact: true,
+                units_in_header: false,
+            };
+            let mut json = Vec::new();
+            report.write(&mut json, false, false).unwrap();
+            json
+        };
+
+        let first = write();
+        for _ in 0..20 {
+            assert_eq!(write(), first);
+        }
+
+        let json: serde_json::Value = serde_json::from_slice(&first).unwrap();
+        let keys: Vec<&str> = json["groups"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|group| group["key"].as_str().unwrap())
+            .collect();
+        assert_eq!(keys, ["md", "txt", "rs", "toml", "(other)"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn json_base64_applies_only_to_paths_that_are_not_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let plain = FileEntry::recorded(PathBuf::from("plain.txt"), 1);
+        let json = file_json(&plain, PathEncoding::Base64);
+        assert_eq!(json["path"], "plain.txt");
+        assert!(json.get("path_b64").is_none());
+
+        let invalid = FileEntry::recorded(PathBuf::from(OsStr::from_bytes(b"bad\xff")), 1);
+        let json = file_json(&invalid, PathEncoding::Base64);
+        assert_eq!(json["path_b64"], "YmFk/w==");
+        assert!(json.get("path").is_none());
+    }
+
+    #[test]
+    fn formats_follow_the_output_extension() {
+        assert!(Format::from_path(Path::new("report.JSON")) == Some(Format::Json));
+        assert!(Format::from_path(Path::new("report.csv")) == Some(Format::Csv));
+        for (name, format) in [("a.tsv", "TSV"), ("a.md", "Markdown"), ("a.html", "HTML")] {
+            assert!(Format::from_path(Path::new(name)).is_none());
+            assert_eq!(Format::unsupported(Path::new(name)), Some(format));
+        }
+        assert_eq!(Format::unsupported(Path::new("report.txt")), None);
+    }
+}
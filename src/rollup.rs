@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::FileEntry;
+
+/// Sums every file's size into each of its ancestor directories and returns
+/// the directories sorted by cumulative size, largest first.
+pub fn cumulative_dir_sizes(entries: &[FileEntry]) -> Vec<(PathBuf, u64)> {
+    let mut totals: HashMap<PathBuf, u64> = HashMap::new();
+
+    for entry in entries {
+        // `./src/a` would otherwise count into both `.` and the empty path,
+        // which also stands for `.`.
+        let path = without_cur_dir(&entry.path);
+        for dir in path.ancestors().skip(1) {
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            *totals.entry(dir.to_path_buf()).or_insert(0) += entry.size;
+        }
+    }
+
+    let mut dirs: Vec<(PathBuf, u64)> = totals.into_iter().collect();
+    dirs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    dirs
+}
+
+/// The path with its `.` components left out.
+fn without_cur_dir(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect()
+}
+
+/// The `n` directories with the largest cumulative size, at any depth.
+/// Absolute paths under `base` are made relative to it, `./src` and `src` are
+/// the same directory, and `base` itself is left out since it always holds
+/// everything.
+pub fn top_dirs(entries: &[FileEntry], n: usize, base: &Path) -> Vec<(PathBuf, u64)> {
+    let relative: Vec<FileEntry> = entries
+        .iter()
+        .map(|entry| {
+            let path = entry.path.strip_prefix(base).unwrap_or(&entry.path);
+            FileEntry::recorded(without_cur_dir(path), entry.size)
+        })
+        .collect();
+
+    let mut dirs = cumulative_dir_sizes(&relative);
+    dirs.retain(|(dir, _)| dir != Path::new("."));
+    dirs.truncate(n);
+    dirs
+}
+
+/// A directory or file with the cumulative size of everything below it.
+pub struct Node {
+    pub name: String,
+    pub size: u64,
+    /// Entries below a directory, largest first. Always empty for files.
+    pub children: Vec<Node>,
+    pub is_dir: bool,
+}
+
+/// Nests the entries by directory under a root named `.`, with absolute
+/// paths under `base` made relative to it.
+pub fn tree(entries: &[FileEntry], base: &Path) -> Node {
+    #[derive(Default)]
+    struct Dir {
+        dirs: HashMap<String, Dir>,
+        files: Vec<(String, u64)>,
+    }
+
+    fn into_node(name: String, dir: Dir) -> Node {
+        let mut children: Vec<Node> = dir
+            .dirs
+            .into_iter()
+            .map(|(name, dir)| into_node(name, dir))
+            .chain(dir.files.into_iter().map(|(name, size)| Node {
+                name,
+                size,
+                children: Vec::new(),
+                is_dir: false,
+            }))
+            .collect();
+        children.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+        Node {
+            name,
+            size: children.iter().map(|child| child.size).sum(),
+            children,
+            is_dir: true,
+        }
+    }
+
+    let mut root = Dir::default();
+    for entry in entries {
+        let path = entry.path.strip_prefix(base).unwrap_or(&entry.path);
+        let components: Vec<String> = path
+            .components()
+            .filter(|component| !matches!(component, Component::CurDir))
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let Some((file, parents)) = components.split_last() else {
+            continue;
+        };
+        let mut dir = &mut root;
+        for parent in parents {
+            dir = dir.dirs.entry(parent.clone()).or_default();
+        }
+        dir.files.push((file.clone(), entry.size));
+    }
+    into_node(".".to_string(), root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dot_prefixed_paths_count_into_the_root_once() {
+        let entries = [
+            FileEntry::recorded(PathBuf::from("./src/a.rs"), 10),
+            FileEntry::recorded(PathBuf::from("b.rs"), 5),
+        ];
+        assert_eq!(
+            cumulative_dir_sizes(&entries),
+            vec![(PathBuf::from("."), 15), (PathBuf::from("src"), 10)]
+        );
+    }
+
+    #[test]
+    fn top_dirs_merge_dot_prefixed_and_plain_paths() {
+        let entries = [
+            FileEntry::recorded(PathBuf::from("./src/a.rs"), 10),
+            FileEntry::recorded(PathBuf::from("src/b.rs"), 5),
+            FileEntry::recorded(PathBuf::from("./docs/c.md"), 12),
+        ];
+        assert_eq!(
+            top_dirs(&entries, 1, Path::new("/base")),
+            vec![(PathBuf::from("src"), 15)]
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use std::env;
+
+/// How human-readable sizes are rendered. Machine output always uses raw
+/// byte counts and never goes through this.
+pub struct SizeFormat {
+    pub decimal_sep: char,
+    /// Print exact byte counts instead of scaled units.
+    pub bytes: bool,
+    /// Fixed width for per-file size fields: zero-padded byte counts, or
+    /// right-aligned human sizes.
+    pub pad: Option<usize>,
+    /// The divisor between units, 1000 or 1024.
+    pub base: u64,
+    /// Label units KiB, MiB, ... instead of KB, MB, ..., whatever the base.
+    pub binary_labels: bool,
+    /// Follow scaled totals with their exact byte count.
+    pub exact: bool,
+}
+
+impl SizeFormat {
+    /// Formats a per-file size field, applying `pad`.
+    pub fn format_field(&self, size: u64) -> String {
+        let formatted = self.format(size);
+        match (self.pad, self.bytes) {
+            (Some(width), true) => format!("{:0>width$}", formatted),
+            (Some(width), false) => format!("{:>width$}", formatted),
+            (None, _) => formatted,
+        }
+    }
+
+    /// Formats a total or subtotal. With `exact`, a size shown in a scaled
+    /// unit is followed by its byte count, e.g. `1.50 GB (1610612736 bytes)`.
+    pub fn format_total(&self, size: u64) -> String {
+        match self.exact_bytes(size) {
+            Some(bytes) => format!("{} ({} bytes)", self.format(size), bytes),
+            None => self.format(size),
+        }
+    }
+
+    /// The byte count `exact` adds after `size`, unless it is already shown
+    /// exactly.
+    pub fn exact_bytes(&self, size: u64) -> Option<u64> {
+        (self.exact && !self.bytes && size >= self.base).then_some(size)
+    }
+
+    /// Formats a size change with an explicit `+` or `-` sign.
+    pub fn format_delta(&self, delta: i128) -> String {
+        let sign = if delta < 0 { '-' } else { '+' };
+        format!("{}{}", sign, self.format(delta.unsigned_abs() as u64))
+    }
+
+    /// Formats `size` in the largest unit it reaches, with two decimals
+    /// rounded half up: 1536 bytes is `1.50 KB` and 1029 bytes (1.0049 KB) is
+    /// `1.00 KB`. Rounding never yields `1024.00` (or `1000.00`) of a unit;
+    /// such values move to the next unit, so 1048575 bytes is `1.00 MB`. Sizes
+    /// below one base are exact byte counts.
+    ///
+    /// The divisor and the labels are independent. For 1500000 bytes:
+    ///
+    /// | base | labels | result      |
+    /// |------|--------|-------------|
+    /// | 1024 | SI     | `1.43 MB`   |
+    /// | 1024 | binary | `1.43 MiB`  |
+    /// | 1000 | SI     | `1.50 MB`   |
+    /// | 1000 | binary | `1.50 MiB`  |
+    ///
+    /// 1024 with SI labels is the default, matching earlier releases.
+    pub fn format(&self, size: u64) -> String {
+        if self.bytes {
+            return size.to_string();
+        }
+
+        const SI_UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+        const BINARY_UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+        let units = if self.binary_labels {
+            BINARY_UNITS
+        } else {
+            SI_UNITS
+        };
+
+        let base = u128::from(self.base);
+        if u128::from(size) < base {
+            return format!("{} {}", size, units[0]);
+        }
+
+        let mut unit_index = 1;
+        let hundredths = loop {
+            let divisor = base.pow(unit_index as u32);
+            let hundredths = (u128::from(size) * 200 + divisor) / (2 * divisor);
+            if hundredths < base * 100 || unit_index == units.len() - 1 {
+                break hundredths;
+            }
+            unit_index += 1;
+        };
+
+        format!(
+            "{}{}{:02} {}",
+            hundredths / 100,
+            self.decimal_sep,
+            hundredths % 100,
+            units[unit_index]
+        )
+    }
+}
+
+/// Parses the `--bytes-base` divisor.
+pub fn parse_base(value: &str) -> Result<u64, String> {
+    match value {
+        "1000" => Ok(1000),
+        "1024" => Ok(1024),
+        _ => Err(format!("expected 1000 or 1024, got '{}'", value)),
+    }
+}
+
+pub fn parse_decimal_sep(value: &str) -> Result<char, String> {
+    match value {
+        "." => Ok('.'),
+        "," => Ok(','),
+        _ => Err("expected '.' or ','".to_string()),
+    }
+}
+
+/// Picks the decimal separator from `LC_ALL`, `LC_NUMERIC` or `LANG`, the
+/// same precedence the C library uses. Falls back to `.`.
+pub fn locale_decimal_sep() -> char {
+    const COMMA_LANGUAGES: &[&str] = &[
+        "bg", "ca", "cs", "da", "de", "el", "es", "et", "fi", "fr", "hr", "hu", "id", "it", "lt",
+        "lv", "nb", "nl", "nn", "no", "pl", "pt", "ro", "ru", "sk", "sl", "sr", "sv", "tr", "uk",
+        "vi",
+    ];
+
+    let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty());
+
+    let Some(locale) = locale else {
+        return '.';
+    };
+    let language = locale
+        .split(['_', '.', '@', '-'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    if COMMA_LANGUAGES.contains(&language.as_str()) {
+        ','
+    } else {
+        '.'
+    }
+}
+
+/// Parses sizes like `512`, `4KB`, `1.5 GB` or `10MiB`. Units are
+/// case-insensitive and use the same 1024 multiples as the displayed sizes.
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", value))?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        _ => return Err(format!("unknown size unit in '{}'", value)),
+    };
+
+    let bytes = number * multiplier as f64;
+    if bytes > u64::MAX as f64 {
+        return Err(format!("size '{}' is too large", value));
+    }
+    Ok(bytes.round() as u64)
+}
+
+/// Parses an allocation unit: a size like [`parse_size`] that must not be zero.
+pub fn parse_unit(value: &str) -> Result<u64, String> {
+    match parse_size(value)? {
+        0 => Err(format!("unit '{}' must be larger than zero", value.trim())),
+        unit => Ok(unit),
+    }
+}
+
+/// How far a total may stray from an expected size, either way.
+#[derive(Clone, Copy)]
+pub enum Tolerance {
+    Bytes(u64),
+    Percent(f64),
+}
+
+impl Tolerance {
+    /// The allowed deviation from `expected`, in bytes.
+    pub fn of(self, expected: u64) -> u64 {
+        match self {
+            Tolerance::Bytes(bytes) => bytes,
+            Tolerance::Percent(percent) => (expected as f64 * percent / 100.0).round() as u64,
+        }
+    }
+}
+
+/// Parses a tolerance: a size like [`parse_size`], or a percentage of the
+/// expected size like `10%` or `2.5%`.
+pub fn parse_tolerance(value: &str) -> Result<Tolerance, String> {
+    let Some(percent) = value.trim().strip_suffix('%') else {
+        return parse_size(value).map(Tolerance::Bytes);
+    };
+    match percent.trim().parse::<f64>() {
+        Ok(percent) if percent.is_finite() && percent >= 0.0 => Ok(Tolerance::Percent(percent)),
+        _ => Err(format!("invalid percentage '{}'", value.trim())),
+    }
+}
+
+/// Rounds `size` up to the next multiple of `unit`.
+pub fn round_up(size: u64, unit: u64) -> Option<u64> {
+    size.div_ceil(unit).checked_mul(unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(base: u64, binary_labels: bool) -> SizeFormat {
+        SizeFormat {
+            decimal_sep: '.',
+            bytes: false,
+            pad: None,
+            base,
+            binary_labels,
+            exact: false,
+        }
+    }
+
+    #[test]
+    fn small_sizes_are_exact_bytes() {
+        let format = format(1024, false);
+        assert_eq!(format.format(0), "0 B");
+        assert_eq!(format.format(1), "1 B");
+        assert_eq!(format.format(1023), "1023 B");
+        assert_eq!(format.format(1024), "1.00 KB");
+    }
+
+    #[test]
+    fn halves_round_up() {
+        let format = format(1024, false);
+        // 1.125 KB and 3.125 KB would round down to even.
+        assert_eq!(format.format(1152), "1.13 KB");
+        assert_eq!(format.format(3200), "3.13 KB");
+        assert_eq!(format.format(1029), "1.00 KB");
+        assert_eq!(format.format(1536), "1.50 KB");
+    }
+
+    #[test]
+    fn rounding_up_to_a_full_unit_moves_to_the_next_unit() {
+        let binary = format(1024, true);
+        // 1048570 bytes is 1023.994 KiB, 1048571 bytes is 1023.995 KiB.
+        assert_eq!(binary.format(1048570), "1023.99 KiB");
+        assert_eq!(binary.format(1048571), "1.00 MiB");
+        assert_eq!(binary.format(1048575), "1.00 MiB");
+
+        let decimal = format(1000, false);
+        assert_eq!(decimal.format(999_994), "999.99 KB");
+        assert_eq!(decimal.format(999_995), "1.00 MB");
+    }
+
+    #[test]
+    fn the_largest_unit_absorbs_everything_above_it() {
+        assert_eq!(format(1024, false).format(u64::MAX), "16777216.00 TB");
+    }
+
+    #[test]
+    fn the_decimal_separator_is_configurable() {
+        let format = SizeFormat {
+            decimal_sep: ',',
+            ..format(1024, false)
+        };
+        assert_eq!(format.format(1536), "1,50 KB");
+    }
+
+    #[test]
+    fn base_and_labels_are_independent() {
+        assert_eq!(format(1024, false).format(1_500_000), "1.43 MB");
+        assert_eq!(format(1024, true).format(1_500_000), "1.43 MiB");
+        assert_eq!(format(1000, false).format(1_500_000), "1.50 MB");
+        assert_eq!(format(1000, true).format(1_500_000), "1.50 MiB");
+    }
+
+    #[test]
+    fn every_unit_has_both_labels() {
+        let si: Vec<String> = (0..5)
+            .map(|power| format(1000, false).format(1000u64.pow(power)))
+            .collect();
+        assert_eq!(si, ["1 B", "1.00 KB", "1.00 MB", "1.00 GB", "1.00 TB"]);
+        let binary: Vec<String> = (0..5)
+            .map(|power| format(1024, true).format(1024u64.pow(power)))
+            .collect();
+        assert_eq!(
+            binary,
+            ["1 B", "1.00 KiB", "1.00 MiB", "1.00 GiB", "1.00 TiB"]
+        );
+    }
+
+    #[test]
+    fn the_base_only_takes_1000_or_1024() {
+        assert_eq!(parse_base("1000"), Ok(1000));
+        assert_eq!(parse_base("1024"), Ok(1024));
+        assert!(parse_base("1000.0").is_err());
+        assert!(parse_base("512").is_err());
+    }
+}
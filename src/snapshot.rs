@@ -0,0 +1,113 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::FileEntry;
+
+const VERSION: u32 = 1;
+
+/// A saved scan result that later runs can be compared against.
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub total_bytes: u64,
+    pub files: Vec<SnapshotFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SnapshotFile {
+    pub path: String,
+    pub bytes: u64,
+}
+
+/// How one file's size differs between a baseline, such as a snapshot, and what
+/// it is compared with. A side is `None` when the file is missing from it.
+pub struct FileChange {
+    pub path: String,
+    pub before: Option<u64>,
+    pub after: Option<u64>,
+}
+
+impl FileChange {
+    /// The size change from the baseline to the compared side.
+    pub fn delta(&self) -> i128 {
+        i128::from(self.after.unwrap_or(0)) - i128::from(self.before.unwrap_or(0))
+    }
+}
+
+impl Snapshot {
+    pub fn from_entries(entries: &[FileEntry], total_bytes: u64) -> Self {
+        Self {
+            version: VERSION,
+            total_bytes,
+            files: entries
+                .iter()
+                .map(|entry| SnapshotFile {
+                    path: entry.path.display().to_string(),
+                    bytes: entry.size,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read snapshot: {}", path.display()))?;
+        let snapshot: Snapshot = serde_json::from_str(&data)
+            .with_context(|| format!("Invalid snapshot file: {}", path.display()))?;
+        if snapshot.version != VERSION {
+            anyhow::bail!(
+                "Unsupported snapshot version {} in {}",
+                snapshot.version,
+                path.display()
+            );
+        }
+        Ok(snapshot)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_string_pretty(self)?;
+        fs::write(path, data)
+            .with_context(|| format!("Failed to write snapshot: {}", path.display()))
+    }
+
+    /// Files that were added, removed or changed size since this snapshot,
+    /// ordered by path.
+    pub fn changes(&self, entries: &[FileEntry]) -> Vec<FileChange> {
+        diff(
+            self.files
+                .iter()
+                .map(|file| (file.path.clone(), file.bytes)),
+            entries
+                .iter()
+                .map(|entry| (entry.path.display().to_string(), entry.size)),
+        )
+    }
+}
+
+/// Files that were added, removed or changed size between two sets of
+/// `(path, size)` pairs, ordered by path.
+pub fn diff(
+    before: impl IntoIterator<Item = (String, u64)>,
+    after: impl IntoIterator<Item = (String, u64)>,
+) -> Vec<FileChange> {
+    let mut sizes: BTreeMap<String, (Option<u64>, Option<u64>)> = BTreeMap::new();
+    for (path, bytes) in before {
+        sizes.entry(path).or_default().0 = Some(bytes);
+    }
+    for (path, bytes) in after {
+        sizes.entry(path).or_default().1 = Some(bytes);
+    }
+
+    sizes
+        .into_iter()
+        .filter(|(_, (before, after))| before != after)
+        .map(|(path, (before, after))| FileChange {
+            path,
+            before,
+            after,
+        })
+        .collect()
+}
@@ -0,0 +1,42 @@
+use clap::ValueEnum;
+use std::cmp::Reverse;
+
+use crate::FileEntry;
+use crate::collate::Collator;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum FileSort {
+    /// Largest first, ties broken by path
+    Size,
+    /// By path
+    Name,
+}
+
+/// Sorts `entries` for display. With a collator, paths are compared by its
+/// collation order, falling back to bytes for paths it considers equal.
+pub fn sort_entries(entries: &mut [FileEntry], sort: FileSort, collator: Option<&Collator>) {
+    if let Some(collator) = collator {
+        match sort {
+            FileSort::Size => entries.sort_by_cached_key(|entry| {
+                (
+                    Reverse(entry.size),
+                    collator.key(&entry.path),
+                    entry.path.clone(),
+                )
+            }),
+            FileSort::Name => {
+                entries.sort_by_cached_key(|entry| (collator.key(&entry.path), entry.path.clone()))
+            }
+        }
+        return;
+    }
+
+    match sort {
+        FileSort::Size => entries.sort_by(|a, b| {
+            Reverse(a.size)
+                .cmp(&Reverse(b.size))
+                .then_with(|| a.path.cmp(&b.path))
+        }),
+        FileSort::Name => entries.sort_by(|a, b| a.path.cmp(&b.path)),
+    }
+}
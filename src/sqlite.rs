@@ -0,0 +1,54 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crate::FileEntry;
+use crate::encoding::PathEncoding;
+use crate::ext::ExtensionRules;
+
+/// Writes one row per file into a `files(path, bytes, ext, mtime)` table of
+/// the SQLite database at `path`, replacing the table if it already exists.
+///
+/// The rows go in through one prepared INSERT inside a single transaction.
+/// `ext` is NULL for files without an extension and `mtime` is in seconds
+/// since the Unix epoch.
+pub fn write(
+    path: &Path,
+    entries: &[FileEntry],
+    rules: &ExtensionRules,
+    encoding: PathEncoding,
+) -> Result<()> {
+    let context = || format!("Failed to write SQLite database {}", path.display());
+    let mut connection = Connection::open(path).with_context(context)?;
+    let transaction = connection.transaction().with_context(context)?;
+    transaction
+        .execute_batch(
+            "DROP TABLE IF EXISTS files;
+             CREATE TABLE files(path TEXT, bytes INTEGER, ext TEXT, mtime INTEGER);",
+        )
+        .with_context(context)?;
+    {
+        let mut insert = transaction
+            .prepare("INSERT INTO files VALUES(?1, ?2, ?3, ?4)")
+            .with_context(context)?;
+        for entry in entries {
+            let mtime = entry
+                .mtime
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .and_then(|age| i64::try_from(age.as_secs()).ok());
+            // SQLite integers are signed 64-bit, which no real file size exceeds.
+            let bytes = i64::try_from(entry.size)
+                .with_context(|| format!("Size too large for SQLite: {}", entry.path.display()))?;
+            insert
+                .execute((
+                    encoding.encode(&entry.path),
+                    bytes,
+                    rules.extension(&entry.path),
+                    mtime,
+                ))
+                .with_context(context)?;
+        }
+    }
+    transaction.commit().with_context(context)
+}
@@ -0,0 +1,48 @@
+use colored::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::theme::Themed;
+
+/// Counters for what each stage of a scan touched, updated from rayon
+/// workers and reported once at the end.
+#[derive(Default)]
+pub struct ScanStats {
+    /// Paths produced by expanding the patterns.
+    pub seen: AtomicUsize,
+    pub directories: AtomicUsize,
+    pub symlinks: AtomicUsize,
+    /// Paths that are not regular files, including directories.
+    pub non_files: AtomicUsize,
+    pub excluded: AtomicUsize,
+    /// Files whose metadata was read.
+    pub stated: AtomicUsize,
+    /// Files dropped by filters such as --filter, --user, --max-path-depth or
+    /// --exclude-larger-than.
+    pub filtered: AtomicUsize,
+    /// Literal paths that do not exist and files that vanished before being measured.
+    pub missing: AtomicUsize,
+}
+
+impl ScanStats {
+    pub fn report(&self) {
+        let rows = [
+            ("Entries seen", &self.seen),
+            ("Directories matched", &self.directories),
+            ("Symlinks encountered", &self.symlinks),
+            ("Skipped as non-files", &self.non_files),
+            ("Skipped by excludes", &self.excluded),
+            ("Files stat'd", &self.stated),
+            ("Dropped by filters", &self.filtered),
+            ("Missing", &self.missing),
+        ];
+
+        eprintln!("\n{}", "--- Scan stats ---".value().bold());
+        for (label, counter) in rows {
+            eprintln!(
+                "{}: {}",
+                label.good(),
+                counter.load(Ordering::Relaxed).to_string().value()
+            );
+        }
+    }
+}
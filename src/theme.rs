@@ -0,0 +1,240 @@
+use clap::ValueEnum;
+use colored::{Color, ColoredString, Colorize};
+use std::sync::OnceLock;
+
+/// A preset palette for terminal output.
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum ThemeName {
+    /// Bright colors for dark backgrounds
+    #[default]
+    Dark,
+    /// Deeper colors that stay readable on light backgrounds
+    Light,
+    /// No color at all, only bold and underline
+    Mono,
+    /// The Solarized accent colors, for either background
+    Solarized,
+}
+
+/// How one kind of text is styled.
+#[derive(Clone, Copy)]
+struct Style {
+    color: Option<Color>,
+    bold: bool,
+    underline: bool,
+    dimmed: bool,
+}
+
+impl Style {
+    const PLAIN: Style = Style {
+        color: None,
+        bold: false,
+        underline: false,
+        dimmed: false,
+    };
+
+    const fn color(color: Color) -> Self {
+        Style {
+            color: Some(color),
+            ..Style::PLAIN
+        }
+    }
+
+    const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Style::color(Color::TrueColor { r, g, b })
+    }
+
+    fn apply(self, text: ColoredString) -> ColoredString {
+        let mut text = match self.color {
+            Some(color) => text.color(color),
+            None => text,
+        };
+        if self.bold {
+            text = text.bold();
+        }
+        if self.underline {
+            text = text.underline();
+        }
+        if self.dimmed {
+            text = text.dimmed();
+        }
+        text
+    }
+
+    /// The same style for the terminal UI.
+    fn to_ratatui(self) -> ratatui::style::Style {
+        use ratatui::style::{Color as Tui, Modifier};
+
+        let mut style = ratatui::style::Style::new();
+        if let Some(color) = self
+            .color
+            .filter(|_| colored::control::SHOULD_COLORIZE.should_colorize())
+        {
+            style = style.fg(match color {
+                Color::Black => Tui::Black,
+                Color::Red => Tui::Red,
+                Color::Green => Tui::Green,
+                Color::Yellow => Tui::Yellow,
+                Color::Blue => Tui::Blue,
+                Color::Magenta => Tui::Magenta,
+                Color::Cyan => Tui::Cyan,
+                Color::White => Tui::Gray,
+                Color::BrightBlack => Tui::DarkGray,
+                Color::BrightRed => Tui::LightRed,
+                Color::BrightGreen => Tui::LightGreen,
+                Color::BrightYellow => Tui::LightYellow,
+                Color::BrightBlue => Tui::LightBlue,
+                Color::BrightMagenta => Tui::LightMagenta,
+                Color::BrightCyan => Tui::LightCyan,
+                Color::BrightWhite => Tui::White,
+                Color::TrueColor { r, g, b } => Tui::Rgb(r, g, b),
+            });
+        }
+        if self.bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if self.underline {
+            style = style.add_modifier(Modifier::UNDERLINED);
+        }
+        if self.dimmed {
+            style = style.add_modifier(Modifier::DIM);
+        }
+        style
+    }
+}
+
+/// The style of every role text plays in the output. All output picks its
+/// styling through these roles, so a preset changes the whole palette.
+pub struct Theme {
+    /// Names of things: field labels, paths, group keys.
+    label: Style,
+    /// Counts, patterns and other values.
+    value: Style,
+    /// Sizes, section names and successful outcomes.
+    good: Style,
+    /// Warnings and notes.
+    warn: Style,
+    /// Errors and thresholds exceeded.
+    bad: Style,
+    /// Grand totals.
+    total: Style,
+    /// Secondary details such as checksums.
+    muted: Style,
+}
+
+impl Theme {
+    pub fn preset(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Dark => Theme {
+                label: Style::color(Color::Blue),
+                value: Style::color(Color::Cyan),
+                good: Style::color(Color::Green),
+                warn: Style::color(Color::Yellow),
+                bad: Style::color(Color::Red),
+                total: Style::color(Color::Magenta),
+                muted: Style {
+                    dimmed: true,
+                    ..Style::PLAIN
+                },
+            },
+            ThemeName::Light => Theme {
+                label: Style::color(Color::Blue),
+                value: Style::color(Color::Magenta),
+                good: Style::rgb(0, 120, 0),
+                warn: Style::rgb(175, 95, 0),
+                bad: Style::color(Color::Red),
+                total: Style::rgb(0, 0, 160),
+                muted: Style::color(Color::BrightBlack),
+            },
+            ThemeName::Mono => Theme {
+                label: Style::PLAIN,
+                value: Style::PLAIN,
+                good: Style::PLAIN,
+                warn: Style {
+                    underline: true,
+                    ..Style::PLAIN
+                },
+                bad: Style {
+                    bold: true,
+                    underline: true,
+                    ..Style::PLAIN
+                },
+                total: Style {
+                    bold: true,
+                    ..Style::PLAIN
+                },
+                muted: Style::PLAIN,
+            },
+            ThemeName::Solarized => Theme {
+                label: Style::rgb(0x26, 0x8b, 0xd2),
+                value: Style::rgb(0x2a, 0xa1, 0x98),
+                good: Style::rgb(0x85, 0x99, 0x00),
+                warn: Style::rgb(0xb5, 0x89, 0x00),
+                bad: Style::rgb(0xdc, 0x32, 0x2f),
+                total: Style::rgb(0xd3, 0x36, 0x82),
+                muted: Style::rgb(0x58, 0x6e, 0x75),
+            },
+        }
+    }
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Selects the palette for the rest of the run. Only the first call counts.
+pub fn set(name: ThemeName) {
+    THEME.get_or_init(|| Theme::preset(name));
+}
+
+fn current() -> &'static Theme {
+    THEME.get_or_init(|| Theme::preset(ThemeName::default()))
+}
+
+/// The selected theme's style for grand totals, for the terminal UI.
+pub fn total_style() -> ratatui::style::Style {
+    current().total.to_ratatui()
+}
+
+/// Styles text by the role it plays, using the selected theme.
+pub trait Themed: Sized {
+    fn into_styled(self) -> ColoredString;
+
+    fn label(self) -> ColoredString {
+        current().label.apply(self.into_styled())
+    }
+
+    fn value(self) -> ColoredString {
+        current().value.apply(self.into_styled())
+    }
+
+    fn good(self) -> ColoredString {
+        current().good.apply(self.into_styled())
+    }
+
+    fn warn(self) -> ColoredString {
+        current().warn.apply(self.into_styled())
+    }
+
+    fn bad(self) -> ColoredString {
+        current().bad.apply(self.into_styled())
+    }
+
+    fn total(self) -> ColoredString {
+        current().total.apply(self.into_styled())
+    }
+
+    fn muted(self) -> ColoredString {
+        current().muted.apply(self.into_styled())
+    }
+}
+
+impl Themed for &str {
+    fn into_styled(self) -> ColoredString {
+        self.into()
+    }
+}
+
+impl Themed for ColoredString {
+    fn into_styled(self) -> ColoredString {
+        self
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::{Condvar, Mutex};
+
+/// A counting semaphore capping how many filesystem calls are in flight at
+/// once, independent of how many threads rayon runs. Without a limit every
+/// call goes through immediately.
+pub struct Throttle {
+    limit: Option<usize>,
+    in_flight: Mutex<usize>,
+    released: Condvar,
+}
+
+impl Throttle {
+    pub fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            in_flight: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Runs `operation` once fewer than `limit` others are running.
+    pub fn run<T>(&self, operation: impl FnOnce() -> T) -> T {
+        let Some(limit) = self.limit else {
+            return operation();
+        };
+
+        {
+            let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+            while *in_flight >= limit {
+                in_flight = self
+                    .released
+                    .wait(in_flight)
+                    .unwrap_or_else(|e| e.into_inner());
+            }
+            *in_flight += 1;
+        }
+
+        let result = operation();
+
+        *self.in_flight.lock().unwrap_or_else(|e| e.into_inner()) -= 1;
+        self.released.notify_one();
+        result
+    }
+}
@@ -0,0 +1,248 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::ext::ExtensionRules;
+use crate::rollup::Node;
+use crate::size::SizeFormat;
+
+const WIDTH: f64 = 1280.0;
+const HEIGHT: f64 = 800.0;
+/// Height of the strip naming a directory above its contents.
+const HEADER: f64 = 14.0;
+/// Gap between a directory's border and its contents.
+const PAD: f64 = 2.0;
+
+#[derive(Clone, Copy)]
+struct Rect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+/// Writes a squarified treemap of `root` to `path` as a standalone SVG.
+/// Every rectangle's area is proportional to its size, directories contain
+/// their entries, and files are colored by extension. Hovering a rectangle
+/// shows its path and size. Rectangles smaller than a pixel are left out.
+pub fn write(
+    path: &Path,
+    root: &Node,
+    rules: &ExtensionRules,
+    size_format: &SizeFormat,
+) -> Result<()> {
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="sans-serif" font-size="11">"#
+    )?;
+    let canvas = Rect {
+        x: 0.0,
+        y: 0.0,
+        w: WIDTH,
+        h: HEIGHT,
+    };
+    let mut treemap = Treemap {
+        svg,
+        rules,
+        size_format,
+    };
+    treemap.node(root, ".", canvas)?;
+    treemap.svg.push_str("</svg>\n");
+
+    fs::write(path, treemap.svg)
+        .with_context(|| format!("Failed to write treemap: {}", path.display()))
+}
+
+struct Treemap<'a> {
+    svg: String,
+    rules: &'a ExtensionRules,
+    size_format: &'a SizeFormat,
+}
+
+impl Treemap<'_> {
+    fn node(&mut self, node: &Node, path: &str, rect: Rect) -> Result<()> {
+        if rect.w < 1.0 || rect.h < 1.0 {
+            return Ok(());
+        }
+        let title = format!("{} ({})", path, self.size_format.format(node.size));
+
+        if !node.is_dir {
+            let hue = self
+                .rules
+                .extension(Path::new(&node.name))
+                .map_or(0, |ext| hue(&ext));
+            let saturation = if hue == 0 { 0 } else { 55 };
+            self.rect(
+                rect,
+                &format!("hsl({hue},{saturation}%,65%)"),
+                "#fff",
+                &title,
+            )?;
+            if rect.w > 40.0 && rect.h > HEADER {
+                self.label(rect, &node.name)?;
+            }
+            return Ok(());
+        }
+
+        self.rect(rect, "#eee", "#888", &title)?;
+        let header = if rect.w > 30.0 && rect.h > HEADER * 2.0 {
+            self.label(rect, &node.name)?;
+            HEADER
+        } else {
+            0.0
+        };
+        let inner = Rect {
+            x: rect.x + PAD,
+            y: rect.y + PAD + header,
+            w: rect.w - PAD * 2.0,
+            h: rect.h - PAD * 2.0 - header,
+        };
+        if inner.w < 1.0 || inner.h < 1.0 {
+            return Ok(());
+        }
+
+        let children: Vec<&Node> = node
+            .children
+            .iter()
+            .filter(|child| child.size > 0)
+            .collect();
+        let sizes: Vec<f64> = children.iter().map(|child| child.size as f64).collect();
+        for (child, rect) in children.into_iter().zip(squarify(&sizes, inner)) {
+            let child_path = if path == "." {
+                child.name.clone()
+            } else {
+                format!("{}/{}", path, child.name)
+            };
+            self.node(child, &child_path, rect)?;
+        }
+        Ok(())
+    }
+
+    fn rect(&mut self, rect: Rect, fill: &str, stroke: &str, title: &str) -> Result<()> {
+        writeln!(
+            self.svg,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}" stroke="{}" stroke-width="0.5"><title>{}</title></rect>"#,
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            fill,
+            stroke,
+            escape(title)
+        )?;
+        Ok(())
+    }
+
+    /// Writes `text` in the top-left corner of `rect`, cut short to roughly
+    /// fit its width.
+    fn label(&mut self, rect: Rect, text: &str) -> Result<()> {
+        let fits = ((rect.w - 4.0) / 6.5).max(0.0) as usize;
+        let text: String = if text.chars().count() > fits {
+            text.chars()
+                .take(fits.saturating_sub(1))
+                .chain(std::iter::once('…'))
+                .collect()
+        } else {
+            text.to_string()
+        };
+        writeln!(
+            self.svg,
+            r#"<text x="{:.1}" y="{:.1}" pointer-events="none">{}</text>"#,
+            rect.x + 3.0,
+            rect.y + 11.0,
+            escape(&text)
+        )?;
+        Ok(())
+    }
+}
+
+/// Lays out `sizes`, largest first, in `rect` with the squarified algorithm
+/// of Bruls, Huizing and van Wijk: items are added to a row along the shorter
+/// side while that improves the row's worst aspect ratio, then the row is
+/// fixed and the rest of the rectangle is filled the same way. The returned
+/// rectangles follow the order of `sizes`.
+fn squarify(sizes: &[f64], rect: Rect) -> Vec<Rect> {
+    let total: f64 = sizes.iter().sum();
+    if total <= 0.0 {
+        return Vec::new();
+    }
+    let scale = rect.w * rect.h / total;
+    let areas: Vec<f64> = sizes.iter().map(|size| size * scale).collect();
+
+    let mut rects = Vec::with_capacity(areas.len());
+    let mut free = rect;
+    let mut start = 0;
+    while start < areas.len() {
+        let side = free.w.min(free.h);
+        let mut end = start + 1;
+        while end < areas.len()
+            && worst(&areas[start..=end], side) <= worst(&areas[start..end], side)
+        {
+            end += 1;
+        }
+
+        let row = &areas[start..end];
+        let row_area: f64 = row.iter().sum();
+        if free.w >= free.h {
+            let thickness = row_area / free.h;
+            let mut y = free.y;
+            for area in row {
+                let h = area / thickness;
+                rects.push(Rect {
+                    x: free.x,
+                    y,
+                    w: thickness,
+                    h,
+                });
+                y += h;
+            }
+            free.x += thickness;
+            free.w -= thickness;
+        } else {
+            let thickness = row_area / free.w;
+            let mut x = free.x;
+            for area in row {
+                let w = area / thickness;
+                rects.push(Rect {
+                    x,
+                    y: free.y,
+                    w,
+                    h: thickness,
+                });
+                x += w;
+            }
+            free.y += thickness;
+            free.h -= thickness;
+        }
+        start = end;
+    }
+    rects
+}
+
+/// The largest aspect ratio among `row` laid along a side of length `side`.
+fn worst(row: &[f64], side: f64) -> f64 {
+    let sum: f64 = row.iter().sum();
+    let max = row.iter().copied().fold(f64::MIN, f64::max);
+    let min = row.iter().copied().fold(f64::MAX, f64::min);
+    let side = side * side;
+    let sum = sum * sum;
+    (side * max / sum).max(sum / (side * min))
+}
+
+/// A stable hue in 1..360 for an extension, so each type keeps its color
+/// across runs. Hue 0 is reserved for files without an extension.
+fn hue(ext: &str) -> u32 {
+    let hash = ext.bytes().fold(2166136261u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(16777619)
+    });
+    1 + hash % 359
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,236 @@
+use anyhow::{Context, Result};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::cmp::Reverse;
+use std::io::IsTerminal;
+
+use crate::FileEntry;
+use crate::ext::ExtensionRules;
+use crate::size::SizeFormat;
+use crate::theme;
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortKey {
+    Size,
+    Name,
+    Ext,
+}
+
+struct Browser<'a> {
+    entries: &'a [FileEntry],
+    extensions: Vec<String>,
+    size_format: &'a SizeFormat,
+    sort: SortKey,
+    reverse: bool,
+    filter: String,
+    editing_filter: bool,
+    visible: Vec<usize>,
+    table: TableState,
+}
+
+/// Shows the collected results in a scrollable table until the user quits.
+///
+/// Keys: arrows/j/k/PgUp/PgDn/Home/End move, `s`/`n`/`e` sort by size, name
+/// or extension, `r` reverses, `/` starts typing a path filter (Enter or Esc
+/// to finish), `q` or Esc quits.
+pub fn run(entries: &[FileEntry], size_format: &SizeFormat, rules: &ExtensionRules) -> Result<()> {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        anyhow::bail!("--interactive needs a terminal; stdin and stdout must not be redirected");
+    }
+
+    let mut browser = Browser {
+        entries,
+        extensions: entries
+            .iter()
+            .map(|entry| rules.extension(&entry.path).unwrap_or_default())
+            .collect(),
+        size_format,
+        sort: SortKey::Size,
+        reverse: false,
+        filter: String::new(),
+        editing_filter: false,
+        visible: Vec::new(),
+        table: TableState::default(),
+    };
+    browser.refresh();
+
+    let mut terminal = match ratatui::try_init() {
+        Ok(terminal) => terminal,
+        Err(err) => {
+            // Undo whatever part of the setup succeeded, such as raw mode.
+            ratatui::restore();
+            return Err(err).context("Failed to set up the terminal");
+        }
+    };
+    let result = browser.event_loop(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl Browser<'_> {
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            if self.editing_filter {
+                match key.code {
+                    KeyCode::Enter | KeyCode::Esc => self.editing_filter = false,
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                        self.refresh();
+                    }
+                    KeyCode::Char(c) => {
+                        self.filter.push(c);
+                        self.refresh();
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('/') => self.editing_filter = true,
+                KeyCode::Char('s') => self.sort_by(SortKey::Size),
+                KeyCode::Char('n') => self.sort_by(SortKey::Name),
+                KeyCode::Char('e') => self.sort_by(SortKey::Ext),
+                KeyCode::Char('r') => {
+                    self.reverse = !self.reverse;
+                    self.refresh();
+                }
+                KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+                KeyCode::PageDown => self.table.scroll_down_by(20),
+                KeyCode::PageUp => self.table.scroll_up_by(20),
+                KeyCode::Home => self.table.select_first(),
+                KeyCode::End => self.table.select_last(),
+                _ => {}
+            }
+        }
+    }
+
+    fn sort_by(&mut self, sort: SortKey) {
+        self.sort = sort;
+        self.refresh();
+    }
+
+    /// Recomputes which entries are shown, and in what order.
+    fn refresh(&mut self) {
+        let filter = self.filter.to_lowercase();
+        self.visible = (0..self.entries.len())
+            .filter(|&index| {
+                filter.is_empty()
+                    || self.entries[index]
+                        .path
+                        .to_string_lossy()
+                        .to_lowercase()
+                        .contains(&filter)
+            })
+            .collect();
+
+        let entries = self.entries;
+        let extensions = &self.extensions;
+        match self.sort {
+            SortKey::Size => self.visible.sort_by(|&a, &b| {
+                Reverse(entries[a].size)
+                    .cmp(&Reverse(entries[b].size))
+                    .then_with(|| entries[a].path.cmp(&entries[b].path))
+            }),
+            SortKey::Name => self
+                .visible
+                .sort_by(|&a, &b| entries[a].path.cmp(&entries[b].path)),
+            SortKey::Ext => self.visible.sort_by(|&a, &b| {
+                extensions[a]
+                    .cmp(&extensions[b])
+                    .then_with(|| entries[a].path.cmp(&entries[b].path))
+            }),
+        }
+        if self.reverse {
+            self.visible.reverse();
+        }
+
+        self.table.select((!self.visible.is_empty()).then_some(0));
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let total: u64 = self
+            .visible
+            .iter()
+            .map(|&index| self.entries[index].size)
+            .sum();
+        let sort = match self.sort {
+            SortKey::Size => "size",
+            SortKey::Name => "name",
+            SortKey::Ext => "ext",
+        };
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                "Total: ".bold(),
+                Span::styled(
+                    self.size_format.format(total),
+                    theme::total_style().add_modifier(Modifier::BOLD),
+                ),
+                format!(
+                    "  {} of {} files  sorted by {}{}",
+                    self.visible.len(),
+                    self.entries.len(),
+                    sort,
+                    if self.reverse { " (reversed)" } else { "" }
+                )
+                .into(),
+            ])),
+            header,
+        );
+
+        let rows = self.visible.iter().map(|&index| {
+            let entry = &self.entries[index];
+            Row::new(vec![
+                self.size_format.format(entry.size),
+                self.extensions[index].clone(),
+                entry.path.display().to_string(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(12),
+                Constraint::Length(8),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(Row::new(vec!["Size", "Ext", "Path"]).bold())
+        .block(Block::default().borders(Borders::TOP | Borders::BOTTOM))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, body, &mut self.table);
+
+        let help = if self.editing_filter {
+            format!("Filter: {}_   (Enter/Esc to finish)", self.filter)
+        } else if self.filter.is_empty() {
+            "s/n/e sort  r reverse  / filter  q quit".to_string()
+        } else {
+            format!(
+                "Filter: {}   s/n/e sort  r reverse  / filter  q quit",
+                self.filter
+            )
+        };
+        frame.render_widget(Paragraph::new(help).dim(), footer);
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+
+use crate::dedup;
+use crate::longpath::fs_path;
+
+/// The pipeline options the reference has to reproduce.
+pub struct Options {
+    pub dedup_overlaps: bool,
+    pub canonical_dedup: bool,
+    pub aggregate_symlinks: bool,
+    pub skip_symlinked_dirs: bool,
+}
+
+/// A deliberately simple, sequential reference for the parallel pipeline,
+/// used by `--verify`. Starts again from every matched path, before any
+/// deduplication, and sums plain `fs::metadata` sizes one path at a time,
+/// sharing none of the pipeline's measuring or accumulation code. `counts`
+/// decides whether a file the filters would keep; files that cannot be read
+/// are left out, as they are from the total. Returns the total size and
+/// file count.
+pub fn reference_total(
+    paths: &[PathBuf],
+    options: &Options,
+    counts: impl Fn(&Path, &Metadata) -> Result<bool>,
+) -> (u64, usize) {
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    for path in paths {
+        // Canonical paths also catch the same path matched twice.
+        let key = if options.canonical_dedup {
+            fs::canonicalize(fs_path(path)).unwrap_or_else(|_| path.clone())
+        } else {
+            path.clone()
+        };
+        if (options.dedup_overlaps || options.canonical_dedup) && !seen.insert(key) {
+            continue;
+        }
+
+        let Ok(link) = fs::symlink_metadata(fs_path(path)) else {
+            continue;
+        };
+        let is_symlink = link.file_type().is_symlink();
+        let metadata = match fs::metadata(fs_path(path)) {
+            Ok(target) if options.skip_symlinked_dirs && is_symlink && target.is_dir() => link,
+            Ok(target) => target,
+            Err(_) => continue,
+        };
+        if !counts(path, &metadata).unwrap_or(false) {
+            continue;
+        }
+        let id = if options.aggregate_symlinks {
+            dedup::file_id(path, &metadata)
+        } else {
+            None
+        };
+        files.push((metadata.len(), id, is_symlink));
+    }
+
+    // A symlink adds nothing when its target was matched itself or through
+    // an earlier link.
+    let mut counted: HashSet<_> = files
+        .iter()
+        .filter(|(_, _, is_symlink)| !is_symlink)
+        .filter_map(|(_, id, _)| id.clone())
+        .collect();
+
+    let mut total = 0u64;
+    let mut count = 0;
+    for (size, id, is_symlink) in files {
+        if is_symlink
+            && let Some(id) = id
+            && !counted.insert(id)
+        {
+            continue;
+        }
+        total = total.saturating_add(size);
+        count += 1;
+    }
+    (total, count)
+}
@@ -0,0 +1,379 @@
+use anyhow::{Context, Result};
+use glob::{MatchOptions, Pattern};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use rayon::prelude::*;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::display::pattern_root;
+
+/// Expands `pattern` by walking its literal root directory, never descending
+/// more than `max_depth` levels below it. Unlike `glob()`, which has no depth
+/// limit, directories below the limit are not even opened. Without a limit a
+/// missing root simply has no matches, as with `glob()`. Unless
+/// `follow_links` is set, symlinked directories are yielded but not entered.
+/// With `skip_hidden`, names starting with a dot below the root are neither
+/// yielded nor, for directories, entered.
+pub fn walk(
+    pattern: &str,
+    max_depth: Option<usize>,
+    follow_links: bool,
+    skip_hidden: bool,
+) -> Result<impl Iterator<Item = Result<PathBuf, walkdir::Error>>> {
+    let matcher =
+        Pattern::new(pattern).with_context(|| format!("Invalid glob pattern: {}", pattern))?;
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::new()
+    };
+
+    let root = pattern_root(pattern);
+    let implicit_root = root.as_os_str().is_empty();
+    let start = if implicit_root {
+        PathBuf::from(".")
+    } else {
+        root
+    };
+    let exists = max_depth.is_some() || crate::longpath::fs_path(&start).is_dir();
+
+    let entries = WalkDir::new(start)
+        .min_depth(1)
+        .max_depth(max_depth.unwrap_or(usize::MAX))
+        .follow_links(follow_links)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(move |entry| !skip_hidden || !is_hidden(entry))
+        .take_while(move |_| exists)
+        .filter_map(move |entry| {
+            let path = match entry {
+                Ok(entry) => entry.into_path(),
+                Err(e) => return Some(Err(e)),
+            };
+            let path = match (implicit_root, path.strip_prefix(".")) {
+                (true, Ok(relative)) => relative.to_path_buf(),
+                _ => path,
+            };
+            matcher
+                .matches_path_with(Path::new(&path), options)
+                .then_some(Ok(path))
+        });
+
+    Ok(entries)
+}
+
+/// One directory walk serving many patterns at once. Patterns whose literal
+/// roots are equal or nested share the walk of the outermost root, and every
+/// entry is matched against all patterns in a single pass, so overlapping
+/// subtrees such as five patterns under `src/**` are only read once.
+pub struct SharedWalk {
+    set: GlobSet,
+    /// The literal root of each pattern in `set`.
+    roots: Vec<PathBuf>,
+    /// Outermost roots to walk, each with how deep it must go.
+    walks: Vec<(PathBuf, usize)>,
+    max_depth: Option<usize>,
+    follow_links: bool,
+    /// Whether hidden names are pruned from the walks, which only happens
+    /// when no pattern names them.
+    skip_hidden: bool,
+}
+
+impl SharedWalk {
+    pub fn new(
+        patterns: &[&str],
+        max_depth: Option<usize>,
+        follow_links: bool,
+        skip_hidden: bool,
+    ) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid glob pattern: {}", pattern))?;
+            builder.add(glob);
+        }
+        let set = builder.build().context("Failed to compile glob patterns")?;
+
+        let roots: Vec<PathBuf> = patterns.iter().map(|p| pattern_root(p)).collect();
+        let mut walks: Vec<(PathBuf, usize)> = Vec::new();
+        for root in &roots {
+            if walks.iter().any(|(walk, _)| covers(walk, root)) {
+                continue;
+            }
+            walks.retain(|(walk, _)| !covers(root, walk));
+            walks.push((root.clone(), 0));
+        }
+        for (walk, depth) in &mut walks {
+            *depth = roots
+                .iter()
+                .filter(|root| covers(walk, root))
+                .map(|root| {
+                    let below = root.components().count() - walk.components().count();
+                    max_depth.map_or(usize::MAX, |max_depth| below.saturating_add(max_depth))
+                })
+                .max()
+                .unwrap_or(usize::MAX);
+        }
+
+        Ok(Self {
+            set,
+            roots,
+            walks,
+            max_depth,
+            follow_links,
+            skip_hidden: skip_hidden
+                && !patterns
+                    .iter()
+                    .any(|pattern| crate::patterns::names_hidden(pattern)),
+        })
+    }
+
+    /// How many directory walks serve all the patterns.
+    pub fn walk_count(&self) -> usize {
+        self.walks.len()
+    }
+
+    /// Walks every root in parallel and returns, for each pattern in the
+    /// order given to `new`, the paths it matches in walk order, followed by
+    /// the errors met while walking. Walks end early once `stop` returns true.
+    pub fn run(&self, stop: impl Fn() -> bool + Sync) -> (Vec<Vec<PathBuf>>, Vec<walkdir::Error>) {
+        let per_walk: Vec<Vec<_>> = self
+            .walks
+            .par_iter()
+            .map(|(root, depth)| self.walk(root, *depth).take_while(|_| !stop()).collect())
+            .collect();
+
+        let mut matched = vec![Vec::new(); self.roots.len()];
+        let mut errors = Vec::new();
+        for result in per_walk.into_iter().flatten() {
+            match result {
+                Ok((path, positions)) => {
+                    for position in positions {
+                        matched[position].push(path.clone());
+                    }
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        (matched, errors)
+    }
+
+    fn walk<'a>(
+        &'a self,
+        root: &Path,
+        depth: usize,
+    ) -> impl Iterator<Item = Result<(PathBuf, Vec<usize>), walkdir::Error>> + 'a {
+        let implicit_root = root.as_os_str().is_empty();
+        let start = if implicit_root {
+            PathBuf::from(".")
+        } else {
+            root.to_path_buf()
+        };
+        // Like `glob()`, a missing root simply has no matches, unless a depth
+        // limit is set, where `walk()` reports it.
+        let exists = self.max_depth.is_some() || crate::longpath::fs_path(&start).is_dir();
+
+        WalkDir::new(start)
+            .min_depth(1)
+            .max_depth(depth)
+            .follow_links(self.follow_links)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| !self.skip_hidden || !is_hidden(entry))
+            .take_while(move |_| exists)
+            .filter_map(move |entry| {
+                let path = match entry {
+                    Ok(entry) => entry.into_path(),
+                    Err(e) => return Some(Err(e)),
+                };
+                let path = match (implicit_root, path.strip_prefix(".")) {
+                    (true, Ok(relative)) => relative.to_path_buf(),
+                    _ => path,
+                };
+                let matches: Vec<usize> = self
+                    .set
+                    .matches(&path)
+                    .into_iter()
+                    .filter(|&position| self.within_depth(position, &path))
+                    .collect();
+                (!matches.is_empty()).then_some(Ok((path, matches)))
+            })
+    }
+
+    fn within_depth(&self, position: usize, path: &Path) -> bool {
+        let Some(max_depth) = self.max_depth else {
+            return true;
+        };
+        path.strip_prefix(&self.roots[position])
+            .is_ok_and(|below| below.components().count() <= max_depth)
+    }
+}
+
+/// How deep `path` lies below `cwd`: the number of components left once
+/// `cwd` is stripped from the front, not counting `.`. A file directly in
+/// `cwd` has depth 1. Paths outside `cwd` count every component below the
+/// filesystem root, each `..` counting as one.
+pub fn path_depth(path: &Path, cwd: &Path) -> usize {
+    path.strip_prefix(cwd)
+        .unwrap_or(path)
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_) | Component::ParentDir))
+        .count()
+}
+
+/// Whether a walked entry below the root has a name starting with a dot.
+fn is_hidden(entry: &walkdir::DirEntry) -> bool {
+    entry.depth() > 0 && entry.file_name().as_encoded_bytes().starts_with(b".")
+}
+
+/// Whether `path` is a symlink to a directory.
+pub fn is_dir_symlink(path: &Path) -> bool {
+    let path = crate::longpath::fs_path(path);
+    fs::symlink_metadata(&path).is_ok_and(|link| link.file_type().is_symlink()) && path.is_dir()
+}
+
+/// Whether walking `outer` also reaches every path under `inner`, with the
+/// same spelling. The implicit root `""` (the current directory) covers
+/// plain relative roots but not ones starting with `.`, `..` or `/`.
+fn covers(outer: &Path, inner: &Path) -> bool {
+    if outer.as_os_str().is_empty() {
+        return matches!(inner.components().next(), None | Some(Component::Normal(_)));
+    }
+    inner.starts_with(outer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A temporary tree with the given files, each one byte long. The
+    /// directory name must not start with a dot, as tempfile's default does.
+    fn tree(files: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::Builder::new().prefix("weight").tempdir().unwrap();
+        for file in files {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"x").unwrap();
+        }
+        dir
+    }
+
+    fn relative(root: &Path, paths: impl IntoIterator<Item = PathBuf>) -> Vec<String> {
+        let mut paths: Vec<String> = paths
+            .into_iter()
+            .map(|path| path.strip_prefix(root).unwrap().display().to_string())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    fn walked(root: &Path, pattern: &str, skip_hidden: bool) -> Vec<String> {
+        let pattern = format!("{}/{}", root.display(), pattern);
+        let paths = walk(&pattern, None, true, skip_hidden).unwrap();
+        relative(root, paths.map(Result::unwrap))
+    }
+
+    #[test]
+    fn walks_prune_hidden_directories() {
+        let dir = tree(&[
+            "a.txt",
+            ".env",
+            ".git/objects/o1",
+            "src/.cache/b.rs",
+            "src/c.rs",
+        ]);
+        assert_eq!(
+            walked(dir.path(), "**/*", true),
+            ["a.txt", "src", "src/c.rs"]
+        );
+        let everything = walked(dir.path(), "**/*", false);
+        assert!(everything.contains(&".git/objects/o1".to_string()));
+        assert!(everything.contains(&"src/.cache/b.rs".to_string()));
+    }
+
+    #[test]
+    fn shared_walks_prune_hidden_directories_unless_a_pattern_names_them() {
+        let dir = tree(&[".env", ".git/config", "src/c.rs"]);
+        let all = format!("{}/**/*", dir.path().display());
+        let (matched, _) = SharedWalk::new(&[&all], None, true, true)
+            .unwrap()
+            .run(|| false);
+        assert_eq!(
+            relative(dir.path(), matched.into_iter().flatten()),
+            ["src", "src/c.rs"]
+        );
+
+        let dotted = format!("{}/**/.*", dir.path().display());
+        let (matched, _) = SharedWalk::new(&[&all, &dotted], None, true, true)
+            .unwrap()
+            .run(|| false);
+        assert!(matched[1].iter().any(|path| path.ends_with(".env")));
+    }
+
+    #[cfg(unix)]
+    fn linked_tree() -> tempfile::TempDir {
+        let dir = tree(&["real/a.txt"]);
+        std::os::unix::fs::symlink(dir.path().join("real"), dir.path().join("link")).unwrap();
+        // A link back up the tree, which would otherwise be walked again
+        // and again.
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("real/up")).unwrap();
+        dir
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_dirs_are_listed_but_not_entered() {
+        let dir = linked_tree();
+        let pattern = format!("{}/**/*", dir.path().display());
+        let paths = walk(&pattern, None, false, true).unwrap();
+        assert_eq!(
+            relative(dir.path(), paths.map(Result::unwrap)),
+            ["link", "real", "real/a.txt", "real/up"]
+        );
+
+        let (matched, errors) = SharedWalk::new(&[&pattern], None, false, true)
+            .unwrap()
+            .run(|| false);
+        assert!(errors.is_empty());
+        assert_eq!(
+            relative(dir.path(), matched.into_iter().flatten()),
+            ["link", "real", "real/a.txt", "real/up"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn followed_links_reach_their_targets() {
+        let dir = linked_tree();
+        let pattern = format!("{}/*/a.txt", dir.path().display());
+        let paths = walk(&pattern, None, true, true).unwrap();
+        let paths: Vec<String> = relative(dir.path(), paths.filter_map(Result::ok));
+        assert_eq!(paths, ["link/a.txt", "real/a.txt"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn only_links_to_directories_are_dir_symlinks() {
+        let dir = linked_tree();
+        std::os::unix::fs::symlink(dir.path().join("real/a.txt"), dir.path().join("file_link"))
+            .unwrap();
+        assert!(is_dir_symlink(&dir.path().join("link")));
+        assert!(is_dir_symlink(&dir.path().join("real/up")));
+        assert!(!is_dir_symlink(&dir.path().join("real")));
+        assert!(!is_dir_symlink(&dir.path().join("file_link")));
+        assert!(!is_dir_symlink(&dir.path().join("missing")));
+    }
+
+    #[test]
+    fn path_depth_counts_components_below_cwd() {
+        let cwd = Path::new("/work/project");
+        assert_eq!(path_depth(Path::new("a.txt"), cwd), 1);
+        assert_eq!(path_depth(Path::new("./a.txt"), cwd), 1);
+        assert_eq!(path_depth(Path::new("src/a.txt"), cwd), 2);
+        assert_eq!(path_depth(Path::new("/work/project/src/a.txt"), cwd), 2);
+        assert_eq!(path_depth(Path::new("../other/a.txt"), cwd), 3);
+        assert_eq!(path_depth(Path::new("/etc/hosts"), cwd), 2);
+    }
+}
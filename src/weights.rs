@@ -0,0 +1,63 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::FileEntry;
+use crate::ext::ExtensionRules;
+
+/// Per-extension cost multipliers from `--weights`, e.g. `dds=4` to count
+/// uncompressed textures at four times their size.
+pub struct Weights {
+    multipliers: HashMap<String, f64>,
+}
+
+impl Weights {
+    /// Reads `file` one `ext=multiplier` pair per line, skipping blank lines
+    /// and lines starting with `#`.
+    pub fn load(file: &Path, rules: &ExtensionRules) -> Result<Self> {
+        let contents = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read weights file: {}", file.display()))?;
+
+        let mut multipliers = HashMap::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || {
+                format!(
+                    "Invalid weight on line {} of {}: expected ext=multiplier, got '{}'",
+                    number + 1,
+                    file.display(),
+                    line
+                )
+            };
+            let (ext, multiplier) = line.split_once('=').with_context(invalid)?;
+            let multiplier: f64 = multiplier.trim().parse().ok().with_context(invalid)?;
+            if !multiplier.is_finite() || multiplier < 0.0 {
+                anyhow::bail!(invalid());
+            }
+            let ext = ext.trim().trim_start_matches('.');
+            multipliers.insert(rules.normalize(ext), multiplier);
+        }
+
+        Ok(Self { multipliers })
+    }
+
+    /// The sum of every file's size times the multiplier of its extension.
+    /// Extensions without a multiplier count at 1.0.
+    pub fn total(&self, entries: &[FileEntry], rules: &ExtensionRules) -> u64 {
+        let total: f64 = entries
+            .iter()
+            .map(|entry| {
+                let multiplier = rules
+                    .extension(&entry.path)
+                    .and_then(|ext| self.multipliers.get(&ext).copied())
+                    .unwrap_or(1.0);
+                entry.size as f64 * multiplier
+            })
+            .sum();
+        total.round() as u64
+    }
+}
@@ -0,0 +1,34 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn path_depth_bounds_are_inclusive() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("b/c/d")).unwrap();
+    // Sizes 1, 10, 100 and 1000 at depths 1 to 4 show which ones are kept.
+    fs::write(dir.path().join("a"), [0u8; 1]).unwrap();
+    fs::write(dir.path().join("b/a"), [0u8; 10]).unwrap();
+    fs::write(dir.path().join("b/c/a"), [0u8; 100]).unwrap();
+    fs::write(dir.path().join("b/c/d/a"), [0u8; 1000]).unwrap();
+
+    let total = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_weight"))
+            .args(["--raw", "**/a"])
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert_eq!(total(&[]), "1111\n");
+    assert_eq!(total(&["--min-path-depth", "2"]), "1110\n");
+    assert_eq!(total(&["--max-path-depth", "2"]), "11\n");
+    assert_eq!(
+        total(&["--min-path-depth", "2", "--max-path-depth", "3"]),
+        "110\n"
+    );
+    assert_eq!(
+        total(&["--min-path-depth", "3", "--max-path-depth", "3"]),
+        "100\n"
+    );
+}
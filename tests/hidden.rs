@@ -0,0 +1,32 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn raw_total(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_weight"))
+        .arg("--raw")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn hidden_files_need_a_dot_or_the_flag() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join(".hidden"), [0u8; 5]).unwrap();
+    fs::write(dir.path().join("visible"), [0u8; 3]).unwrap();
+    fs::create_dir(dir.path().join(".git")).unwrap();
+    fs::write(dir.path().join(".git/object"), [0u8; 100]).unwrap();
+
+    assert_eq!(raw_total(dir.path(), &["*"]), "3\n");
+    assert_eq!(raw_total(dir.path(), &["**/*"]), "3\n");
+    assert_eq!(raw_total(dir.path(), &[".*"]), "5\n");
+    assert_eq!(raw_total(dir.path(), &["--hidden", "*"]), "8\n");
+    assert_eq!(raw_total(dir.path(), &["--hidden", "**/*"]), "108\n");
+    assert_eq!(
+        raw_total(dir.path(), &["--parallel-glob-once", "**/*"]),
+        "3\n"
+    );
+}
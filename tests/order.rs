@@ -0,0 +1,36 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn candidates_follow_pattern_order() {
+    let dir = tempfile::tempdir().unwrap();
+    for sub in ["a", "b", "c"] {
+        fs::create_dir(dir.path().join(sub)).unwrap();
+        for i in 0..50 {
+            fs::write(dir.path().join(sub).join(format!("{:02}", i)), b"x").unwrap();
+        }
+    }
+    let patterns = ["c/*", "a/*", "b/*", "a/*"];
+    let expected: Vec<String> = patterns
+        .iter()
+        .flat_map(|pattern| (0..50).map(move |i| format!("{}/{:02}", &pattern[..1], i)))
+        .collect();
+
+    // Rayon's scheduling differs from run to run, so a few runs give an
+    // order that depends on it a chance to show.
+    for _ in 0..10 {
+        let output = Command::new(env!("CARGO_BIN_EXE_weight"))
+            .args(["--list", "--threads", "8"])
+            .args(patterns)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let listed: Vec<String> = String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        assert_eq!(listed, expected);
+    }
+}
@@ -0,0 +1,38 @@
+use std::fs;
+use std::process::Command;
+
+fn weight(dir: &std::path::Path, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_weight"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn raw_prints_a_bare_integer() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.png"), [0u8; 1000]).unwrap();
+    fs::write(dir.path().join("b.png"), [0u8; 2345]).unwrap();
+    fs::write(dir.path().join("c.txt"), [0u8; 7]).unwrap();
+
+    let output = weight(dir.path(), &["--raw", "*.png"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "3345\n");
+}
+
+#[test]
+fn raw_keeps_diagnostics_off_stdout() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.png"), [0u8; 10]).unwrap();
+
+    let output = weight(
+        dir.path(),
+        &["--raw", "--allow-missing", "*.png", "missing.png"],
+    );
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "10\n");
+
+    let output = weight(dir.path(), &["--raw", "*.jpg"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "0\n");
+    assert!(!output.stderr.is_empty());
+}